@@ -5,7 +5,7 @@ use move_compiler::{
     expansion::ast::ModuleAccess,
     hlir::ast::*,
     naming::ast::{BuiltinTypeName, BuiltinTypeName_, TParam},
-    parser::ast::{BinOp, BinOp_, UnaryOp},
+    parser::ast::{BinOp, BinOp_, Field, UnaryOp},
 };
 use move_ir_types::location::Loc;
 
@@ -40,6 +40,17 @@ impl AstTsPrinter for Exp {
                                     // vector needs explicit copy
                                     BuiltinTypeName_::Vector => explicit_copy,
                                     BuiltinTypeName_::Bool => Ok(nocopy),
+                                    // bigints are immutable values
+                                    BuiltinTypeName_::U8
+                                    | BuiltinTypeName_::U16
+                                    | BuiltinTypeName_::U32
+                                    | BuiltinTypeName_::U64
+                                    | BuiltinTypeName_::U128
+                                    | BuiltinTypeName_::U256
+                                        if c.is_bigint() =>
+                                    {
+                                        Ok(nocopy)
+                                    }
                                     // immutable types, address, signer do not need explicit copy
                                     //_ => Ok(nocopy),
                                     _ => explicit_copy,
@@ -103,21 +114,34 @@ impl AstTsPrinter for Exp {
                 // op_u64(l, r)
                 (l, op, r).term(c)
             }
-            E::Borrow(_, e, f) => {
-                if c.is_async() {
-                    Ok(format!("({}).{}", e.term(c)?, rename(f)))
+            E::Borrow(is_mut, e, f) => {
+                if *is_mut && is_mut_ref_type(exp_ty, c) {
+                    Ok(format!("$.fieldRef({}, \"{}\")", e.term(c)?, rename(f)))
                 } else {
-                    Ok(format!("{}.{}", e.term(c)?, rename(f)))
+                    field_place(e, f, c)
                 }
             }
-            E::BorrowLocal(_, v) => {
-                Ok(rename(v))
-                //Ok(rename(v))
+            E::BorrowLocal(is_mut, v) => {
+                if *is_mut && is_mut_ref_type(exp_ty, c) {
+                    let name = rename(v);
+                    Ok(format!(
+                        "$.mutRef(() => {}, ($v) => {{ {} = $v; }})",
+                        name, name
+                    ))
+                } else {
+                    Ok(rename(v))
+                }
             }
             E::Cast(e, ty) => Ok(format!("{}({})", builtin_cast_name(ty, c)?, e.term(c)?)),
             E::Spec(_, _) => Ok("".to_string()),
-            // FIXME: is this really how freeze should behave?
-            E::Freeze(e) => e.term(c),
+            // immutable references are plain values, only a MutRef needs unwrapping
+            E::Freeze(e) => {
+                if is_mut_ref_type(&e.ty, c) {
+                    Ok(format!("$.freeze({})", e.term(c)?))
+                } else {
+                    e.term(c)
+                }
+            }
             E::UnresolvedError => {
                 derr!((exp.loc, "Encountered UnresolvedError"))
             }
@@ -126,6 +150,44 @@ impl AstTsPrinter for Exp {
     }
 }
 
+/// Whether `&mut` of a value of this type is a MutRef. Integers, vectors and structs are updated
+/// in place through the object itself, but bool and address values are not, nor are integers
+/// with --numbers bigint; type parameters may be either, which the runtime decides when the
+/// reference is taken.
+pub fn needs_mut_ref(ty: &BaseType, c: &Context) -> bool {
+    match &ty.value {
+        BaseType_::Param(_) => true,
+        BaseType_::Apply(_, typename, _) => match &typename.value {
+            TypeName_::Builtin(builtin) => match builtin.value {
+                BuiltinTypeName_::Bool | BuiltinTypeName_::Address => true,
+                _ => c.is_bigint() && bigint_width(&builtin.value).is_some(),
+            },
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Whether `ty` is a mutable reference that may be a MutRef
+pub fn is_mut_ref_type(ty: &Type, c: &Context) -> bool {
+    match &ty.value {
+        Type_::Single(single) => match &single.value {
+            SingleType_::Ref(true, base) => needs_mut_ref(base, c),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// `e.f` as a value, or as the target of an assignment
+pub fn field_place(e: &Exp, f: &Field, c: &mut Context) -> TermResult {
+    if c.is_async() {
+        Ok(format!("({}).{}", e.term(c)?, rename(f)))
+    } else {
+        Ok(format!("{}.{}", e.term(c)?, rename(f)))
+    }
+}
+
 pub fn format_type_args_at_instantiation(type_args: &Vec<BaseType>, c: &mut Context) -> TermResult {
     if type_args.is_empty() {
         return Ok("".to_string());
@@ -157,7 +219,7 @@ impl AstTsPrinter for ModuleCall {
 
         // every function signature will start with:
         // export function X($c: AptosDataCache, $p: TypeTag[], ...)
-        let func_name = format_qualified_name(module, name, c);
+        let func_name = format_qualified_name(module, name, name.0.loc, c)?;
         // AptosFramework::TypeInfo::type_of requires us to always send in the type parameters as
         // arguments as they are not always inferrable from regular parameters
         let tparams_ = format_type_args_at_instantiation(type_arguments, c)?;
@@ -253,9 +315,13 @@ impl AstTsPrinter for ExpListItem {
     fn term(&self, c: &mut Context) -> TermResult {
         match self {
             ExpListItem::Single(e, _) => e.term(c),
-            ExpListItem::Splat(_, _e, _ss) => {
-                panic!("Splat not implemented");
-            }
+            // a call returning multiple values, passed on as consecutive items. Tuples are
+            // translated to arrays typed as TS tuples, so they can be spread in place, both into
+            // argument lists and into array literals
+            ExpListItem::Splat(loc, e, _) => match &e.ty.value {
+                Type_::Multiple(_) => Ok(format!("...{}", e.term(c)?)),
+                _ => derr!((*loc, "Splat of an expression that is not a tuple")),
+            },
         }
     }
 }
@@ -290,7 +356,7 @@ pub fn base_type_to_tstype(base_ty: &BaseType, c: &mut Context) -> TermResult {
                 }
                 TypeName_::ModuleType(mi, s) => {
                     // in TS, do not include type args
-                    Ok(format_qualified_name(mi, s, c))
+                    format_qualified_name(mi, s, base_ty.loc, c)
                 }
             }
         }
@@ -343,35 +409,65 @@ impl AstTsPrinter for TypeName {
     fn term(&self, c: &mut Context) -> TermResult {
         match &self.value {
             TypeName_::Builtin(bt) => bt.term(c),
-            TypeName_::ModuleType(m, s) => Ok(format_qualified_name(m, s, c)),
+            TypeName_::ModuleType(m, s) => format_qualified_name(m, s, self.loc, c),
         }
     }
 }
 
 impl AstTsPrinter for BuiltinTypeName {
     const CTOR_NAME: &'static str = "BuiltinTypeName";
-    fn term(&self, _c: &mut Context) -> TermResult {
+    fn term(&self, c: &mut Context) -> TermResult {
+        if c.is_bigint() && bigint_width(&self.value).is_some() {
+            return Ok("bigint".to_string());
+        }
         match &self.value {
             BuiltinTypeName_::Address => Ok("HexString".to_string()),
             BuiltinTypeName_::Bool => Ok("boolean".to_string()),
             BuiltinTypeName_::U8 => Ok("U8".to_string()),
+            BuiltinTypeName_::U16 => Ok("U16".to_string()),
+            BuiltinTypeName_::U32 => Ok("U32".to_string()),
             BuiltinTypeName_::U64 => Ok("U64".to_string()),
             BuiltinTypeName_::U128 => Ok("U128".to_string()),
+            BuiltinTypeName_::U256 => Ok("U256".to_string()),
             BuiltinTypeName_::Signer => Ok("HexString".to_string()),
-            BuiltinTypeName_::Vector => {
-                panic!("Should be handled elsewhere as here we do not have type param info");
-            }
+            BuiltinTypeName_::Vector => derr!((
+                self.loc,
+                "Vector type without its element type, which should be handled elsewhere"
+            )),
         }
     }
 }
 
-pub fn builtin_cast_name(builtin: &BuiltinTypeName, _c: &mut Context) -> TermResult {
+/// Suffix of the runtime helpers for an integer type with --numbers bigint, e.g. `$.checkU64`.
+/// None for types that are not integers.
+pub fn bigint_width(builtin: &BuiltinTypeName_) -> Option<&'static str> {
+    match builtin {
+        BuiltinTypeName_::U8 => Some("U8"),
+        BuiltinTypeName_::U16 => Some("U16"),
+        BuiltinTypeName_::U32 => Some("U32"),
+        BuiltinTypeName_::U64 => Some("U64"),
+        BuiltinTypeName_::U128 => Some("U128"),
+        BuiltinTypeName_::U256 => Some("U256"),
+        _ => None,
+    }
+}
+
+pub fn builtin_cast_name(builtin: &BuiltinTypeName, c: &mut Context) -> TermResult {
+    if c.is_bigint() {
+        // a cast only needs to check that the value fits
+        if let Some(width) = bigint_width(&builtin.value) {
+            return Ok(format!("$.check{}", width));
+        }
+    }
     match builtin.value {
         BuiltinTypeName_::Address => derr!((builtin.loc, "Cannot cast to address")),
         BuiltinTypeName_::Bool => derr!((builtin.loc, "Cannot cast to bool")),
         BuiltinTypeName_::U8 => Ok("u8".to_string()),
+        BuiltinTypeName_::U16 => Ok("u16".to_string()),
+        BuiltinTypeName_::U32 => Ok("u32".to_string()),
         BuiltinTypeName_::U64 => Ok("u64".to_string()),
         BuiltinTypeName_::U128 => Ok("u128".to_string()),
+        BuiltinTypeName_::U256 => Ok("u256".to_string()),
         BuiltinTypeName_::Signer => derr!((builtin.loc, "Cannot cast to signer")),
         BuiltinTypeName_::Vector => derr!((builtin.loc, "Cannot cast to vector")),
     }
@@ -469,8 +565,8 @@ impl AstTsPrinter for BinOp {
     }
 }
 
-pub fn dynamic_binop_name(op: BinOp_) -> &'static str {
-    match op {
+pub fn dynamic_binop_name(op: &BinOp) -> Result<&'static str, Diagnostic> {
+    Ok(match op.value {
         BinOp_::Add => "$.dyn_add",
         BinOp_::Sub => "$.dyn_sub",
         BinOp_::Mul => "$.dyn_mul",
@@ -489,8 +585,8 @@ pub fn dynamic_binop_name(op: BinOp_) -> &'static str {
         BinOp_::Gt => "$.dyn_gt",
         BinOp_::Le => "$.dyn_le",
         BinOp_::Ge => "$.dyn_ge",
-        _ => panic!("Unsupported binop: {}", op),
-    }
+        _ => return derr!((op.loc, format!("Unsupported binop: {}", op))),
+    })
 }
 
 pub fn handle_binop_for_base_type(
@@ -534,8 +630,52 @@ pub fn handle_binop_for_base_type(
                                 rhs.term(c)?,
                             ))
                         }
+                        // native bigints: only the results that may leave the range of the type
+                        // need to be checked
+                        BuiltinTypeName_::U8
+                        | BuiltinTypeName_::U16
+                        | BuiltinTypeName_::U32
+                        | BuiltinTypeName_::U64
+                        | BuiltinTypeName_::U128
+                        | BuiltinTypeName_::U256
+                            if c.is_bigint() =>
+                        {
+                            let width = bigint_width(&builtin.value).unwrap();
+                            let (lhs, rhs) = (lhs.term(c)?, rhs.term(c)?);
+                            match binop.value {
+                                BinOp_::Eq => Ok(format!("({} === {})", lhs, rhs)),
+                                BinOp_::Neq => Ok(format!("({} !== {})", lhs, rhs)),
+                                BinOp_::Gt
+                                | BinOp_::Lt
+                                | BinOp_::Ge
+                                | BinOp_::Le
+                                | BinOp_::BitOr
+                                | BinOp_::BitAnd
+                                | BinOp_::Xor
+                                // division by zero throws a RangeError
+                                | BinOp_::Div
+                                | BinOp_::Mod => Ok(format!("({} {} {})", lhs, binop, rhs)),
+                                BinOp_::Add | BinOp_::Sub | BinOp_::Mul => Ok(format!(
+                                    "$.check{}({} {} {})",
+                                    width, lhs, binop, rhs
+                                )),
+                                BinOp_::Shl => Ok(format!("$.shl{}({}, {})", width, lhs, rhs)),
+                                BinOp_::Shr => Ok(format!("$.shr{}({}, {})", width, lhs, rhs)),
+                                _ => {
+                                    derr!((
+                                        binop.loc,
+                                        "Unsupported binary operation between unsigned integers"
+                                    ))
+                                }
+                            }
+                        }
                         // precision-sensitive
-                        BuiltinTypeName_::U8 | BuiltinTypeName_::U64 | BuiltinTypeName_::U128 => {
+                        BuiltinTypeName_::U8
+                        | BuiltinTypeName_::U16
+                        | BuiltinTypeName_::U32
+                        | BuiltinTypeName_::U64
+                        | BuiltinTypeName_::U128
+                        | BuiltinTypeName_::U256 => {
                             match binop.value {
                                 // operate directly using bigInt since they cannot go wrong
                                 BinOp_::Eq => {
@@ -621,7 +761,7 @@ pub fn handle_binop_for_base_type(
             }
         }
         BaseType_::Param(tp) => {
-            let fname = dynamic_binop_name(binop.value);
+            let fname = dynamic_binop_name(binop)?;
             let tparams_ = format!("$p[{}]", c.get_tparam_index(tp).unwrap());
             Ok(format!(
                 "{}({}, {}, {})",
@@ -631,7 +771,10 @@ pub fn handle_binop_for_base_type(
                 rhs.term(c)?
             ))
         }
-        _ => panic!("Not sure how to handle binop for {}", lhs.ty.term(c)?),
+        _ => derr!((
+            ty.loc,
+            format!("Not sure how to handle binop for {}", lhs.ty.term(c)?)
+        )),
     }
 }
 
@@ -647,8 +790,22 @@ pub fn handle_binop_for_type(
         Type_::Multiple(_) => derr!((ty.loc, "Tuple type has no supported binop")),
         Type_::Single(single_ty) => match &single_ty.value {
             SingleType_::Ref(_, ty) => match &binop.value {
+                // either side may be a MutRef: compare the values they point to
+                BinOp_::Eq | BinOp_::Neq if needs_mut_ref(ty, c) => Ok(format!(
+                    "({}$.deep_eq($.copy({}), $.copy({})))",
+                    if matches!(binop.value, BinOp_::Neq) {
+                        "!"
+                    } else {
+                        ""
+                    },
+                    lhs.term(c)?,
+                    rhs.term(c)?
+                )),
                 BinOp_::Eq | BinOp_::Neq => handle_binop_for_base_type(ty, binop, lhs, rhs, c),
-                _ => panic!("Reftype does not have this binop: {}", binop),
+                _ => derr!((
+                    binop.loc,
+                    format!("Reftype does not have this binop: {}", binop)
+                )),
             },
             SingleType_::Base(base_ty) => handle_binop_for_base_type(base_ty, binop, lhs, rhs, c),
         },
@@ -676,7 +833,7 @@ impl AstTsPrinter for ModuleAccess {
         use move_compiler::expansion::ast::ModuleAccess_ as MA;
         match &self.value {
             MA::Name(n) => Ok(format!("{}", n)),
-            MA::ModuleAccess(m, n) => Ok(format_qualified_name(m, n, c)),
+            MA::ModuleAccess(m, n) => format_qualified_name(m, n, self.loc, c),
         }
     }
 }
@@ -686,12 +843,26 @@ impl AstTsPrinter for Value {
     const CTOR_NAME: &'static str = "Value";
     fn term(&self, c: &mut Context) -> TermResult {
         use Value_ as V;
+        if c.is_bigint() {
+            match &self.value {
+                V::U8(u) => return Ok(format!("{}n", u)),
+                V::U16(u) => return Ok(format!("{}n", u)),
+                V::U32(u) => return Ok(format!("{}n", u)),
+                V::U64(u) => return Ok(format!("{}n", u)),
+                V::U128(u) => return Ok(format!("{}n", u)),
+                V::U256(u) => return Ok(format!("{}n", u)),
+                _ => (),
+            }
+        }
         match &self.value {
             V::Address(addr) => ts_format_numerical_address(addr),
-            // FIXME bigInt needs type cast when assigned to U8/64/128?
+            // FIXME bigInt needs type cast when assigned to U8/16/32/64/128/256?
             V::U8(u) => Ok(format!("u8(\"{}\")", u)),
+            V::U16(u) => Ok(format!("u16(\"{}\")", u)),
+            V::U32(u) => Ok(format!("u32(\"{}\")", u)),
             V::U64(u) => Ok(format!("u64(\"{}\")", u)),
             V::U128(u) => Ok(format!("u128(\"{}\")", u)),
+            V::U256(u) => Ok(format!("u256(\"{}\")", u)),
             V::Bool(b) => Ok(format!("{}", b)),
             V::Vector(_, values) => {
                 let mut vals = vec![];
@@ -708,15 +879,39 @@ impl AstTsPrinter for Value {
 impl AstTsPrinter for move_compiler::expansion::ast::Value {
     // Native Literals
     const CTOR_NAME: &'static str = "Value";
-    fn term(&self, _c: &mut Context) -> TermResult {
+    fn term(&self, c: &mut Context) -> TermResult {
         use move_compiler::expansion::ast::Value_ as V;
+        if c.is_bigint() {
+            match &self.value {
+                V::InferredNum(u) => return Ok(format!("{}n", u)),
+                V::U8(u) => return Ok(format!("{}n", u)),
+                V::U16(u) => return Ok(format!("{}n", u)),
+                V::U32(u) => return Ok(format!("{}n", u)),
+                V::U64(u) => return Ok(format!("{}n", u)),
+                V::U128(u) => return Ok(format!("{}n", u)),
+                V::U256(u) => return Ok(format!("{}n", u)),
+                V::Bytearray(v) => {
+                    return Ok(format!(
+                        "[{}]",
+                        v.iter()
+                            .map(|val| format!("{}n", val))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                }
+                _ => (),
+            }
+        }
         match &self.value {
             V::Address(addr) => ts_format_address_as_literal(addr, self.loc),
-            // FIXME bigInt needs type cast when assigned to U8/64/128?
+            // FIXME bigInt needs type cast when assigned to U8/16/32/64/128/256?
             V::InferredNum(u) => Ok(format!("bigInt(\"{}\")", u)),
             V::U8(u) => Ok(format!("u8(\"{}\")", u)),
+            V::U16(u) => Ok(format!("u16(\"{}\")", u)),
+            V::U32(u) => Ok(format!("u32(\"{}\")", u)),
             V::U64(u) => Ok(format!("u64(\"{}\")", u)),
             V::U128(u) => Ok(format!("u128(\"{}\")", u)),
+            V::U256(u) => Ok(format!("u256(\"{}\")", u)),
             V::Bool(b) => Ok(format!("{}", b)),
             V::Bytearray(v) => {
                 let mut vals = vec![];
@@ -1,5 +1,6 @@
 use crate::shared::*;
 use crate::tsgen_writer::TsgenWriter;
+use crate::utils::import_specifier;
 use itertools::Itertools;
 use move_compiler::{
     diagnostics::{Diagnostic, Diagnostics},
@@ -96,8 +97,11 @@ pub fn get_abort_code_from_expected_failure(expected_failure: &Attribute) -> Str
                         use move_compiler::expansion::ast::Value_ as V;
                         return match &v.value {
                             V::U8(u) => quote(u),
+                            V::U16(u) => quote(u),
+                            V::U32(u) => quote(u),
                             V::U64(u) => quote(u),
                             V::U128(u) => quote(u),
+                            V::U256(u) => quote(u),
                             V::InferredNum(u) => quote(u),
                             _ => "".to_string(),
                         };
@@ -112,13 +116,21 @@ pub fn get_abort_code_from_expected_failure(expected_failure: &Attribute) -> Str
 pub fn write_tests(w: &mut TsgenWriter, c: &mut Context) -> WriteResult {
     let mident = c.current_module.unwrap();
 
-    w.writeln(format!(
-        "import * as Source from '../../{}/{}'; ",
+    let source_path = format!(
+        "../../{}/{}",
         format_address(mident.value.address),
         mident.value.module
+    );
+    w.writeln(format!(
+        "import * as Source from '{}'; ",
+        import_specifier(&source_path, false, c.config.module())
     ));
     w.writeln("import * as $ from '@manahippo/move-to-ts';");
     w.writeln("import { HexString } from 'aptos';");
+    w.writeln(format!(
+        "import * as $addresses from '{}';",
+        import_specifier("../../addresses", false, c.config.module())
+    ));
 
     w.new_line();
 
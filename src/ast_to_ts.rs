@@ -2,7 +2,9 @@ use crate::ast_exp::*;
 use crate::ast_tests::check_test;
 use crate::shared::*;
 use crate::tsgen_writer::TsgenWriter;
-use crate::utils::{capitalize, get_iterable_table_helper_decl, get_table_helper_decl, rename};
+use crate::utils::{
+    capitalize, get_iterable_table_helper_decl, get_table_helper_decl, import_specifier, rename,
+};
 use itertools::Itertools;
 use move_compiler::parser::ast::Field;
 use move_compiler::shared::Name;
@@ -16,11 +18,13 @@ use move_compiler::{
 use move_ir_types::location::Loc;
 use std::collections::BTreeSet;
 
+/// Returns the filename and content of the module, along with the Move location each line of the
+/// content was generated from
 pub fn translate_module(
     mident: ModuleIdent,
     mdef: &ModuleDefinition,
     c: &mut Context,
-) -> Result<(String, String), Diagnostics> {
+) -> Result<(String, String, Vec<Option<Loc>>), Diagnostics> {
     let filename = format!(
         "{}/{}.ts",
         format_address(mident.value.address),
@@ -34,39 +38,56 @@ pub fn translate_module(
             diags.add(diag);
             Err(diags)
         }
-        Ok(res) => Ok((filename, res)),
+        Ok((res, line_locs)) => Ok((filename, res, line_locs)),
     }
 }
 
-pub fn to_ts_string(v: &impl AstTsPrinter, c: &mut Context) -> Result<String, Diagnostic> {
+pub fn to_ts_string(
+    v: &impl AstTsPrinter,
+    c: &mut Context,
+) -> Result<(String, Vec<Option<Loc>>), Diagnostic> {
     let mut writer = TsgenWriter::new();
     v.write_ts(&mut writer, c)?;
     let mut lines = vec![
         "import * as $ from \"@manahippo/move-to-ts\";".to_string(),
         "import {AptosDataCache, AptosParserRepo, DummyCache, AptosLocalCache} from \"@manahippo/move-to-ts\";".to_string(),
-        "import {U8, U64, U128} from \"@manahippo/move-to-ts\";".to_string(),
-        "import {u8, u64, u128} from \"@manahippo/move-to-ts\";".to_string(),
+        "import {U8, U16, U32, U64, U128, U256} from \"@manahippo/move-to-ts\";".to_string(),
+        "import {u8, u16, u32, u64, u128, u256} from \"@manahippo/move-to-ts\";".to_string(),
         "import {TypeParamDeclType, FieldDeclType} from \"@manahippo/move-to-ts\";".to_string(),
         "import {AtomicTypeTag, StructTag, TypeTag, VectorTag, SimpleStructTag} from \"@manahippo/move-to-ts\";"
             .to_string(),
         "import {HexString, AptosClient, AptosAccount} from \"aptos\";".to_string(),
+        format!(
+            "import * as $addresses from \"{}\";",
+            import_specifier("../addresses", false, c.config.module())
+        ),
     ];
     for package_name in c.package_imports.iter() {
         lines.push(format!(
-            "import * as {} from \"../{}\";",
+            "import * as {} from \"{}\";",
+            capitalize(package_name),
+            import_specifier(&format!("../{}", package_name), true, c.config.module())
+        ));
+    }
+    for (package_name, npm_module) in c.external_imports.iter() {
+        lines.push(format!(
+            "import {{ {} as {}$ext }} from \"{}\";",
+            package_name,
             capitalize(package_name),
-            package_name
+            npm_module
         ));
     }
     for module_name in c.same_package_imports.iter() {
         lines.push(format!(
-            "import * as {} from \"./{}\";",
+            "import * as {} from \"{}\";",
             capitalize(module_name),
-            module_name
+            import_specifier(&format!("./{}", module_name), false, c.config.module())
         ));
     }
+    let mut line_locs = vec![None; lines.len()];
+    line_locs.extend(writer.line_locs());
     lines.push(format!("{}", writer));
-    Ok(lines.join("\n"))
+    Ok((lines.join("\n"), line_locs))
 }
 
 pub fn handle_special_module(
@@ -110,30 +131,37 @@ impl AstTsPrinter for (ModuleIdent, &ModuleDefinition) {
 
         // module meta
         w.export_const("packageName", quote(&package_name));
-        w.export_const(
-            "moduleAddress",
-            format!(
-                "new HexString({})",
-                quote(&format_address_hex(name.value.address))
-            ),
-        );
+        match address_name(name.value.address) {
+            Some(address_name) => {
+                // a live binding, so that importers see the address passed to setAddresses
+                w.writeln(format!(
+                    "export let moduleAddress = $addresses.peekAddress({});",
+                    quote(&address_name)
+                ));
+                w.writeln(format!(
+                    "$addresses.onAddressChange({}, (address) => {{ moduleAddress = address; }});",
+                    quote(&address_name)
+                ));
+            }
+            None => w.export_const("moduleAddress", ts_address(name.value.address)),
+        }
         w.export_const("moduleName", quote(&name.value.module.0));
         w.new_line();
 
         // constants
         for (cname, cdef) in constants.key_cloned_iter() {
-            (cname, cdef).write_ts(w, c)?;
+            w.with_loc(cdef.loc, |w| (cname, cdef).write_ts(w, c))?;
         }
         w.new_line();
 
         // structs
         for (sname, sdef) in structs.key_cloned_iter() {
-            (sname, sdef).write_ts(w, c)?;
+            w.with_loc(sname.0.loc, |w| (sname, sdef).write_ts(w, c))?;
         }
 
         // functions
         for (fname, fdef) in functions.key_cloned_iter() {
-            (fname, fdef).write_ts(w, c)?;
+            w.with_loc(fname.0.loc, |w| (fname, fdef).write_ts(w, c))?;
         }
 
         // loadParsers
@@ -158,22 +186,33 @@ pub fn write_load_parsers(
     _c: &mut Context,
 ) -> WriteResult {
     w.writeln("export function loadParsers(repo: AptosParserRepo) {");
+    w.increase_indent();
 
+    // parsers are keyed by address, so they are added again under the address passed to
+    // setAddresses
+    let address = match address_name(mident.value.address) {
+        Some(address_name) => {
+            w.writeln(format!(
+                "$addresses.withAddress({}, (address) => {{",
+                quote(&address_name)
+            ));
+            w.increase_indent();
+            "address"
+        }
+        None => "moduleAddress",
+    };
     for (sname, _) in module.structs.key_cloned_iter() {
-        let paramless_name = format!(
-            "{}::{}::{}",
-            format_address_hex(mident.value.address),
-            mident.value.module,
-            sname
-        );
         w.writeln(format!(
-            "  repo.addParser({}, {}.{}Parser);",
-            quote(&paramless_name),
-            sname,
-            sname
+            "repo.addParser(`${{{}.hex()}}::{}::{}`, {}.{}Parser);",
+            address, mident.value.module, sname, sname, sname
         ));
     }
+    if address_name(mident.value.address).is_some() {
+        w.decrease_indent();
+        w.writeln("});");
+    }
 
+    w.decrease_indent();
     w.writeln("}");
 
     Ok(())
@@ -657,6 +696,7 @@ pub fn handle_struct_method_directive(
                 }
                 let func = func_opt.unwrap();
                 validate_method(sname, sdef, fname, func, c)?;
+                c.add_method(&c.current_module.unwrap(), sname, fname);
 
                 // generate method
                 w.new_line();
@@ -738,7 +778,7 @@ impl AstTsPrinter for (StructName, &StructDefinition) {
         w.new_line();
         w.writeln(format!("export class {} ", name.term(c)?));
         w.short_block(|w| {
-            w.writeln("static moduleAddress = moduleAddress;");
+            w.writeln("static get moduleAddress() { return moduleAddress; }");
             w.writeln("static moduleName = moduleName;");
             w.writeln("__app: $.AppType | null = null;");
             w.writeln(format!("static structName: string = {};", quote(&name.term(c)?)));
@@ -835,7 +875,8 @@ impl AstTsPrinter for (StructName, &StructDefinition) {
                         w.new_line();
                         w.writeln("static getTag(): StructTag {");
                         w.writeln(format!(
-                            "  return new StructTag(moduleAddress, moduleName, {}, []);",
+                            "  return new StructTag({}, moduleName, {}, []);",
+                            ts_address(c.current_module.unwrap().value.address),
                             quote(name)
                         ));
                         w.writeln("}");
@@ -845,7 +886,8 @@ impl AstTsPrinter for (StructName, &StructDefinition) {
                         w.new_line();
                         w.writeln("static makeTag($p: TypeTag[]): StructTag {");
                         w.writeln(format!(
-                            "  return new StructTag(moduleAddress, moduleName, {}, $p);",
+                            "  return new StructTag({}, moduleName, {}, $p);",
+                            ts_address(c.current_module.unwrap().value.address),
                             quote(name)
                         ));
                         w.writeln("}");
@@ -1242,17 +1284,29 @@ impl AstTsPrinter for (FunctionName, &Function) {
             FunctionBody_::Native => {
                 let mident = c.current_module.unwrap();
                 let native_name = format!(
-                    "return $.{}_{}_{}",
+                    "$.{}_{}_{}",
                     format_address_(mident.value.address, false),
                     mident.value.module,
                     name
                 );
-                let args = func
-                    .signature
-                    .parameters
-                    .iter()
-                    .map(|(n, _)| rename(&n.to_string()))
-                    .join(", ");
+                let mut args = vec![];
+                // &mut arguments boxed in place, to be written back after the call
+                let mut mut_args = vec![];
+                for (n, ty) in func.signature.parameters.iter() {
+                    let name = rename(&n.to_string());
+                    let base = single_type_base(ty);
+                    if c.is_bigint()
+                        && matches!(ty.value, SingleType_::Ref(true, _))
+                        && may_hold_int(base, false)
+                    {
+                        let boxed_name = format!("$boxed_{}", name);
+                        args.push(boxed_name.clone());
+                        mut_args.push((name, boxed_name, base_type_to_typetag(base, c)?));
+                    } else {
+                        args.push(convert_numbers("boxed", name, base, false, c)?);
+                    }
+                }
+                let args = args.join(", ");
                 let args_comma = format!("{}{}", args, if args.is_empty() { "" } else { ", " });
                 let comma_tags = format!(
                     "{}{}",
@@ -1269,22 +1323,70 @@ impl AstTsPrinter for (FunctionName, &Function) {
                         )
                     }
                 );
+                let from_bytecode = c
+                    .program
+                    .modules
+                    .get(&mident)
+                    .map_or(false, |mdef| !mdef.is_source_module);
                 w.short_block(|w| {
-                    if mident.value.module.to_string().contains("ristretto") {
-                        w.writeln("throw 'Not Implemented';");
+                    if from_bytecode {
+                        w.writeln(format!(
+                            "throw new Error(\"{}::{}::{} was generated from bytecode and has no body\");",
+                            format_address(mident.value.address),
+                            mident.value.module,
+                            name
+                        ));
                     } else {
-                        w.writeln(format!("{}({}$c{});", native_name, args_comma, comma_tags));
+                        let call = format!("{}({}$c{})", native_name, args_comma, comma_tags);
+                        if mut_args.is_empty() {
+                            w.writeln(format!(
+                                "return {};",
+                                convert_native_result(call, &func.signature.return_type, c)?
+                            ));
+                        } else {
+                            for (name, boxed_name, tag) in mut_args.iter() {
+                                w.writeln(format!(
+                                    "const {} = $.boxedInPlace({}, {});",
+                                    boxed_name, name, tag
+                                ));
+                            }
+                            w.writeln(format!("const $result = {};", call));
+                            for (name, boxed_name, tag) in mut_args.iter() {
+                                w.writeln(format!(
+                                    "$.writeBack({}, {}, {});",
+                                    name, boxed_name, tag
+                                ));
+                            }
+                            w.writeln(format!(
+                                "return {};",
+                                convert_native_result(
+                                    "$result".to_string(),
+                                    &func.signature.return_type,
+                                    c
+                                )?
+                            ));
+                        }
                     }
                     Ok(())
                 })?;
             }
+            FunctionBody_::Defined { .. } if c.config.surface_only && !is_entry => {
+                write_surface_only_body(name, w, c);
+            }
             FunctionBody_::Defined { locals, body } => {
                 let new_vars = locals
                     .key_cloned_iter()
                     .map(|(name, _)| name)
                     .filter(|name| !param_names.contains(&name.to_string()))
                     .collect::<Vec<_>>();
-                write_func_body(body, &new_vars, w, c)?;
+                let mark = w.mark();
+                if let Err(diag) = write_func_body(body, &new_vars, w, c) {
+                    if !c.config.lenient {
+                        return Err(diag);
+                    }
+                    w.rollback(mark);
+                    write_stub_body(name, diag, w, c);
+                }
             }
         }
         w.new_line();
@@ -1315,7 +1417,6 @@ impl AstTsPrinter for (FunctionName, &Function) {
 
             w.short_block(|w| {
                 let mident = c.current_module.unwrap();
-                let address = format_address_hex(mident.value.address);
                 if num_tparams > 0 {
                     w.writeln("const typeParamStrings = $p.map(t=>$.getTypeTagFullname(t));");
                 } else {
@@ -1323,7 +1424,7 @@ impl AstTsPrinter for (FunctionName, &Function) {
                 }
                 w.writeln("return $.buildPayload(");
                 // address
-                w.writeln(format!("  new HexString(\"{}\"),", address));
+                w.writeln(format!("  {},", ts_address(mident.value.address)));
                 // moduleName
                 w.writeln(format!("  \"{}\",", mident.value.module));
                 // funcName
@@ -1335,8 +1436,15 @@ impl AstTsPrinter for (FunctionName, &Function) {
                     w.writeln("  [],");
                 } else {
                     w.writeln("  [");
-                    for (pname, _) in params_no_signers.iter() {
-                        w.writeln(format!("    {},", pname,));
+                    for (pname, ty) in params_no_signers.iter() {
+                        let arg = convert_numbers(
+                            "boxed",
+                            pname.to_string(),
+                            single_type_base(ty),
+                            true,
+                            c,
+                        )?;
+                        w.writeln(format!("    {},", arg));
                     }
                     w.writeln("  ],");
                 }
@@ -1355,6 +1463,78 @@ impl AstTsPrinter for (FunctionName, &Function) {
     }
 }
 
+fn single_type_base(ty: &SingleType) -> &BaseType {
+    match &ty.value {
+        SingleType_::Base(base) | SingleType_::Ref(_, base) => base,
+    }
+}
+
+/// Whether a value of this type may hold integers. Fields of structs are not looked into. Type
+/// parameters are included only if `include_params`.
+fn may_hold_int(ty: &BaseType, include_params: bool) -> bool {
+    match &ty.value {
+        BaseType_::Param(_) => include_params,
+        BaseType_::Apply(_, typename, type_args) => match &typename.value {
+            TypeName_::Builtin(builtin) => match builtin.value {
+                BuiltinTypeName_::Bool | BuiltinTypeName_::Address | BuiltinTypeName_::Signer => {
+                    false
+                }
+                BuiltinTypeName_::Vector => type_args
+                    .iter()
+                    .any(|type_arg| may_hold_int(type_arg, include_params)),
+                _ => true,
+            },
+            TypeName_::ModuleType(_, _) => true,
+        },
+        _ => false,
+    }
+}
+
+/// With --numbers bigint, `value` converted by the runtime's `$.boxed` or `$.unboxed`: natives
+/// and payload builders work on U8...U256 objects. Values of type parameters are passed to
+/// natives as they are, which store them without looking at them. `&mut` arguments of natives
+/// are instead boxed in place and written back after the call (`$.boxedInPlace`, `$.writeBack`).
+fn convert_numbers(
+    func: &str,
+    value: String,
+    ty: &BaseType,
+    include_params: bool,
+    c: &mut Context,
+) -> TermResult {
+    if c.is_bigint() && may_hold_int(ty, include_params) {
+        Ok(format!(
+            "$.{}({}, {})",
+            func,
+            value,
+            base_type_to_typetag(ty, c)?
+        ))
+    } else {
+        Ok(value)
+    }
+}
+
+/// The result of a native call, converted back to bigints with --numbers bigint
+fn convert_native_result(call: String, return_type: &Type, c: &mut Context) -> TermResult {
+    match &return_type.value {
+        Type_::Unit => Ok(call),
+        Type_::Single(ty) => convert_numbers("unboxed", call, single_type_base(ty), true, c),
+        Type_::Multiple(tys) => {
+            if c.is_bigint()
+                && tys
+                    .iter()
+                    .any(|ty| may_hold_int(single_type_base(ty), true))
+            {
+                let tags = comma_term(tys, c, |ty, c| {
+                    base_type_to_typetag(single_type_base(ty), c)
+                })?;
+                Ok(format!("$.unboxedTuple({}, [{}])", call, tags))
+            } else {
+                Ok(call)
+            }
+        }
+    }
+}
+
 pub fn extract_builtin_from_base_type(
     ty: &BaseType,
 ) -> Result<(&BuiltinTypeName_, &Vec<BaseType>), bool> {
@@ -1393,8 +1573,11 @@ pub fn get_ts_handler_for_script_function_param(name: &Var, ty: &SingleType) ->
             BuiltinTypeName_::Bool
             | BuiltinTypeName_::Address
             | BuiltinTypeName_::U8
+            | BuiltinTypeName_::U16
+            | BuiltinTypeName_::U32
             | BuiltinTypeName_::U64
-            | BuiltinTypeName_::U128 => Ok(format!("$.payloadArg({})", name)),
+            | BuiltinTypeName_::U128
+            | BuiltinTypeName_::U256 => Ok(format!("$.payloadArg({})", name)),
             BuiltinTypeName_::Signer => unreachable!(),
             BuiltinTypeName_::Vector => {
                 // handle vector
@@ -1406,8 +1589,11 @@ pub fn get_ts_handler_for_script_function_param(name: &Var, ty: &SingleType) ->
                         BuiltinTypeName_::U8 => Ok(format!("$.u8ArrayArg({})", name)),
                         BuiltinTypeName_::Bool
                         | BuiltinTypeName_::Address
+                        | BuiltinTypeName_::U16
+                        | BuiltinTypeName_::U32
                         | BuiltinTypeName_::U64
-                        | BuiltinTypeName_::U128 => {
+                        | BuiltinTypeName_::U128
+                        | BuiltinTypeName_::U256 => {
                             Ok(format!("{}.map(element => $.payloadArg(element))", name))
                         }
                         BuiltinTypeName_::Signer => unreachable!(),
@@ -1452,8 +1638,11 @@ pub fn get_ts_handler_for_vector_in_vector(inner_ty: &BaseType) -> TermResult {
             BuiltinTypeName_::U8 => Ok("array => $.u8ArrayArg(array)".to_string()),
             BuiltinTypeName_::Bool
             | BuiltinTypeName_::Address
+            | BuiltinTypeName_::U16
+            | BuiltinTypeName_::U32
             | BuiltinTypeName_::U64
-            | BuiltinTypeName_::U128 => {
+            | BuiltinTypeName_::U128
+            | BuiltinTypeName_::U256 => {
                 Ok("array => array.map(ele => $.payloadArg(ele))".to_string())
             }
             BuiltinTypeName_::Signer => unreachable!(),
@@ -1612,6 +1801,56 @@ pub fn write_func_body(
     Ok(())
 }
 
+/// --lenient: body for a function that could not be translated. The diagnostic is kept in the
+/// context and reported at the end.
+pub fn write_stub_body(
+    name: &FunctionName,
+    diag: Diagnostic,
+    w: &mut TsgenWriter,
+    c: &mut Context,
+) {
+    let mut diags = Diagnostics::new();
+    diags.add(diag);
+    let msg = diags
+        .clone()
+        .into_codespan_format()
+        .into_iter()
+        .map(|(_, _, (_, msg), _, _)| msg)
+        .join("; ");
+    c.diagnostics.extend(diags);
+
+    let mident = c.current_module.unwrap();
+    let error = format!(
+        "move-to-ts could not translate {}::{}::{}: {}",
+        format_address(mident.value.address),
+        mident.value.module,
+        name,
+        msg
+    );
+    write_throwing_body(&error, w);
+}
+
+/// --surface-only: body for a non-entry function, whose logic is not generated
+pub fn write_surface_only_body(name: &FunctionName, w: &mut TsgenWriter, c: &mut Context) {
+    let mident = c.current_module.unwrap();
+    let error = format!(
+        "{}::{}::{} is not generated with --surface-only",
+        format_address(mident.value.address),
+        mident.value.module,
+        name
+    );
+    write_throwing_body(&error, w);
+}
+
+fn write_throwing_body(error: &str, w: &mut TsgenWriter) {
+    w.writeln("{");
+    w.writeln(format!(
+        "  throw new Error({});",
+        serde_json::to_string(error).unwrap()
+    ));
+    w.writeln("}");
+}
+
 impl AstTsPrinter for Block {
     const CTOR_NAME: &'static str = "Block";
     fn write_ts(&self, w: &mut TsgenWriter, c: &mut Context) -> WriteResult {
@@ -1635,8 +1874,8 @@ impl AstTsPrinter for Statement {
         use Statement_ as S;
         // some value-yielding Block can be formatted as lambdas, and need statements to be
         // presented in the form of ts_term
-        match &self.value {
-            S::Command(cmd) => cmd.write_ts(w, c),
+        w.with_loc(self.loc, |w| match &self.value {
+            S::Command(cmd) => w.with_loc(cmd.loc, |w| cmd.write_ts(w, c)),
             S::IfElse {
                 cond,
                 if_block,
@@ -1687,7 +1926,7 @@ impl AstTsPrinter for Statement {
                 w.write("while (true) ");
                 block.write_ts(w, c)
             }
-        }
+        })
     }
 }
 
@@ -1717,12 +1956,16 @@ impl AstTsPrinter for Command {
                 }
             }
             C::Mutate(lhs, rhs) => match &lhs.exp.value {
-                // DerefAssign
-                UnannotatedExp_::Borrow(_, _, _) => {
-                    w.writeln(format!("{} = {};", lhs.term(c)?, rhs.term(c)?));
+                // DerefAssign: borrows of fields and locals are assigned to directly
+                UnannotatedExp_::Borrow(_, e, f) => {
+                    w.writeln(format!("{} = {};", field_place(e, f, c)?, rhs.term(c)?));
+                }
+                UnannotatedExp_::BorrowLocal(_, v) => {
+                    w.writeln(format!("{} = {};", rename(v), rhs.term(c)?));
                 }
-                UnannotatedExp_::Dereference(_) => {
-                    return derr!((lhs.exp.loc, "Dereference in Mutate not implemented yet"));
+                // any other reference is an object updated in place, or a MutRef
+                UnannotatedExp_::Dereference(e) => {
+                    w.writeln(format!("$.set({}, {});", e.term(c)?, rhs.term(c)?));
                 }
                 _ => {
                     w.writeln(format!("$.set({}, {});", lhs.term(c)?, rhs.term(c)?));
@@ -1746,8 +1989,15 @@ impl AstTsPrinter for Command {
                     w.writeln(format!("{};", exp.term(c)?));
                 }
             }
-            _ => {
-                return derr!((self.loc, "Unsupported Command (Jump)"));
+            // jumps are only introduced when the compiler lowers HLIR to a control flow graph.
+            // HLIR control flow is structured, and break/continue always target the innermost
+            // loop, as they do in TypeScript.
+            C::Jump { .. } | C::JumpIf { .. } => {
+                return derr!((
+                    self.loc,
+                    "Unstructured jump: only structured control flow (if, while, loop, break, \
+                     continue) can be translated"
+                ));
             }
         }
         Ok(())
@@ -0,0 +1,376 @@
+use crate::config::parse_address_mapping;
+use crate::shared::MoveToTsOptions;
+use anyhow::{anyhow, bail, Context as _};
+use move_binary_format::access::ModuleAccess;
+use move_binary_format::file_format::{
+    AbilitySet as BytecodeAbilitySet, CompiledModule, SignatureToken, StructFieldInformation,
+    StructHandleIndex, Visibility as BytecodeVisibility,
+};
+use move_command_line_common::address::NumericalAddress;
+use move_command_line_common::files::FileHash;
+use move_command_line_common::parser::NumberFormat;
+use move_compiler::diagnostics::FilesSourceText;
+use move_compiler::expansion::ast::{AbilitySet, Address, ModuleIdent, ModuleIdent_, Visibility};
+use move_compiler::hlir::ast::*;
+use move_compiler::naming::ast::{BuiltinTypeName_, StructTypeParameter, TParam, TParamID};
+use move_compiler::parser::ast::{Ability_, Field, FunctionName, ModuleName, StructName, Var};
+use move_compiler::shared::unique_map::UniqueMap;
+use move_core_types::account_address::AccountAddress;
+use move_core_types::language_storage::ModuleId;
+use move_ir_types::location::{sp, Loc};
+use move_symbol_pool::Symbol;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const BYTECODE_EXTENSION: &str = "mv";
+
+/// .mv files given directly, or found (recursively) in the given directories
+fn collect_bytecode_files(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    fn visit(path: &Path, found: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+        if path.is_dir() {
+            let mut entries = std::fs::read_dir(path)
+                .with_context(|| format!("Failed to read {}", path.to_string_lossy()))?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            entries.sort();
+            for entry in entries.iter() {
+                if entry.is_dir()
+                    || entry
+                        .extension()
+                        .map_or(false, |ext| ext == BYTECODE_EXTENSION)
+                {
+                    visit(entry, found)?;
+                }
+            }
+        } else {
+            found.push(path.to_path_buf());
+        }
+        Ok(())
+    }
+
+    let mut found = vec![];
+    for path in paths.iter() {
+        visit(path, &mut found)?;
+    }
+    Ok(found)
+}
+
+/// Parses --bytecode-address NAME=ADDRESS pairs
+fn parse_address_names(
+    config: &MoveToTsOptions,
+) -> anyhow::Result<BTreeMap<AccountAddress, Symbol>> {
+    let mut names = BTreeMap::new();
+    for mapping in config.bytecode_address.iter() {
+        let (name, address) = parse_address_mapping(mapping)?;
+        names.insert(address, Symbol::from(name));
+    }
+    Ok(names)
+}
+
+fn convert_abilities(abilities: BytecodeAbilitySet, loc: Loc) -> AbilitySet {
+    let mut converted = vec![];
+    if abilities.has_copy() {
+        converted.push(sp(loc, Ability_::Copy));
+    }
+    if abilities.has_drop() {
+        converted.push(sp(loc, Ability_::Drop));
+    }
+    if abilities.has_store() {
+        converted.push(sp(loc, Ability_::Store));
+    }
+    if abilities.has_key() {
+        converted.push(sp(loc, Ability_::Key));
+    }
+    AbilitySet::from_abilities(converted).unwrap()
+}
+
+fn make_tparam(idx: usize, abilities: BytecodeAbilitySet, loc: Loc) -> TParam {
+    TParam {
+        id: TParamID::next(),
+        user_specified_name: sp(loc, Symbol::from(format!("T{}", idx))),
+        abilities: convert_abilities(abilities, loc),
+    }
+}
+
+/// Translates bytecode signatures into HLIR types, in terms of the ModuleIdents of the loaded
+/// modules
+struct Converter<'a> {
+    module: &'a CompiledModule,
+    module_idents: &'a BTreeMap<ModuleId, ModuleIdent>,
+    loc: Loc,
+}
+
+impl Converter<'_> {
+    fn builtin(&self, name: BuiltinTypeName_, abilities: BytecodeAbilitySet) -> BaseType {
+        self.apply(abilities, TypeName_::Builtin(sp(self.loc, name)), vec![])
+    }
+
+    fn apply(
+        &self,
+        abilities: BytecodeAbilitySet,
+        name: TypeName_,
+        args: Vec<BaseType>,
+    ) -> BaseType {
+        sp(
+            self.loc,
+            BaseType_::Apply(
+                convert_abilities(abilities, self.loc),
+                sp(self.loc, name),
+                args,
+            ),
+        )
+    }
+
+    fn struct_type(&self, idx: StructHandleIndex, args: Vec<BaseType>) -> anyhow::Result<BaseType> {
+        let handle = self.module.struct_handle_at(idx);
+        let module_id = self
+            .module
+            .module_id_for_handle(self.module.module_handle_at(handle.module));
+        let mident = self.module_idents.get(&module_id).ok_or_else(|| {
+            anyhow!(
+                "{} is used by {} but its bytecode was not provided",
+                module_id,
+                self.module.self_id()
+            )
+        })?;
+        let sname = StructName(sp(
+            self.loc,
+            Symbol::from(self.module.identifier_at(handle.name).as_str()),
+        ));
+        Ok(self.apply(
+            handle.abilities,
+            TypeName_::ModuleType(*mident, sname),
+            args,
+        ))
+    }
+
+    fn base_type(&self, token: &SignatureToken, tparams: &[TParam]) -> anyhow::Result<BaseType> {
+        use SignatureToken as T;
+        Ok(match token {
+            T::Bool => self.builtin(BuiltinTypeName_::Bool, BytecodeAbilitySet::PRIMITIVES),
+            T::U8 => self.builtin(BuiltinTypeName_::U8, BytecodeAbilitySet::PRIMITIVES),
+            T::U16 => self.builtin(BuiltinTypeName_::U16, BytecodeAbilitySet::PRIMITIVES),
+            T::U32 => self.builtin(BuiltinTypeName_::U32, BytecodeAbilitySet::PRIMITIVES),
+            T::U64 => self.builtin(BuiltinTypeName_::U64, BytecodeAbilitySet::PRIMITIVES),
+            T::U128 => self.builtin(BuiltinTypeName_::U128, BytecodeAbilitySet::PRIMITIVES),
+            T::U256 => self.builtin(BuiltinTypeName_::U256, BytecodeAbilitySet::PRIMITIVES),
+            T::Address => self.builtin(BuiltinTypeName_::Address, BytecodeAbilitySet::PRIMITIVES),
+            T::Signer => self.builtin(BuiltinTypeName_::Signer, BytecodeAbilitySet::SIGNER),
+            T::Vector(inner) => {
+                let inner = self.base_type(inner, tparams)?;
+                self.apply(
+                    BytecodeAbilitySet::VECTOR,
+                    TypeName_::Builtin(sp(self.loc, BuiltinTypeName_::Vector)),
+                    vec![inner],
+                )
+            }
+            T::Struct(idx) => self.struct_type(*idx, vec![])?,
+            T::StructInstantiation(idx, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.base_type(arg, tparams))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                self.struct_type(*idx, args)?
+            }
+            T::TypeParameter(idx) => sp(self.loc, BaseType_::Param(tparams[*idx as usize].clone())),
+            T::Reference(_) | T::MutableReference(_) => {
+                bail!("Unexpected reference type in {}", self.module.self_id())
+            }
+        })
+    }
+
+    fn single_type(
+        &self,
+        token: &SignatureToken,
+        tparams: &[TParam],
+    ) -> anyhow::Result<SingleType> {
+        Ok(match token {
+            SignatureToken::Reference(inner) => sp(
+                self.loc,
+                SingleType_::Ref(false, self.base_type(inner, tparams)?),
+            ),
+            SignatureToken::MutableReference(inner) => sp(
+                self.loc,
+                SingleType_::Ref(true, self.base_type(inner, tparams)?),
+            ),
+            _ => sp(self.loc, SingleType_::Base(self.base_type(token, tparams)?)),
+        })
+    }
+
+    fn structs(&self) -> anyhow::Result<UniqueMap<StructName, StructDefinition>> {
+        let mut structs = UniqueMap::new();
+        for def in self.module.struct_defs().iter() {
+            let handle = self.module.struct_handle_at(def.struct_handle);
+            let type_parameters = handle
+                .type_parameters
+                .iter()
+                .enumerate()
+                .map(|(idx, tp)| StructTypeParameter {
+                    is_phantom: tp.is_phantom,
+                    param: make_tparam(idx, tp.constraints, self.loc),
+                })
+                .collect::<Vec<_>>();
+            let tparams = type_parameters
+                .iter()
+                .map(|tp| tp.param.clone())
+                .collect::<Vec<_>>();
+            let fields = match &def.field_information {
+                StructFieldInformation::Native => StructFields::Native(self.loc),
+                StructFieldInformation::Declared(fields) => StructFields::Defined(
+                    fields
+                        .iter()
+                        .map(|field| {
+                            let name = self.module.identifier_at(field.name).as_str();
+                            let ty = self.base_type(&field.signature.0, &tparams)?;
+                            Ok((Field(sp(self.loc, Symbol::from(name))), ty))
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?,
+                ),
+            };
+            let name = self.module.identifier_at(handle.name).as_str();
+            let sdef = StructDefinition {
+                attributes: UniqueMap::new(),
+                abilities: convert_abilities(handle.abilities, self.loc),
+                type_parameters,
+                fields,
+            };
+            structs
+                .add(StructName(sp(self.loc, Symbol::from(name))), sdef)
+                .map_err(|_| anyhow!("Duplicate struct {} in {}", name, self.module.self_id()))?;
+        }
+        Ok(structs)
+    }
+
+    fn functions(&self) -> anyhow::Result<UniqueMap<FunctionName, Function>> {
+        let mut functions = UniqueMap::new();
+        for def in self.module.function_defs().iter() {
+            let handle = self.module.function_handle_at(def.function);
+            let type_parameters = handle
+                .type_parameters
+                .iter()
+                .enumerate()
+                .map(|(idx, abilities)| make_tparam(idx, *abilities, self.loc))
+                .collect::<Vec<_>>();
+            // parameter names are not kept in bytecode
+            let parameters = self
+                .module
+                .signature_at(handle.parameters)
+                .0
+                .iter()
+                .enumerate()
+                .map(|(idx, token)| {
+                    let var = Var(sp(self.loc, Symbol::from(format!("arg{}", idx))));
+                    Ok((var, self.single_type(token, &type_parameters)?))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let returns = self
+                .module
+                .signature_at(handle.return_)
+                .0
+                .iter()
+                .map(|token| self.single_type(token, &type_parameters))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let return_type = match returns.len() {
+                0 => sp(self.loc, Type_::Unit),
+                1 => sp(self.loc, Type_::Single(returns.into_iter().next().unwrap())),
+                _ => sp(self.loc, Type_::Multiple(returns)),
+            };
+            let visibility = match def.visibility {
+                BytecodeVisibility::Public => Visibility::Public(self.loc),
+                BytecodeVisibility::Friend => Visibility::Friend(self.loc),
+                BytecodeVisibility::Private => Visibility::Internal,
+            };
+            let name = self.module.identifier_at(handle.name).as_str();
+            let func = Function {
+                attributes: UniqueMap::new(),
+                visibility,
+                entry: if def.is_entry { Some(self.loc) } else { None },
+                signature: FunctionSignature {
+                    type_parameters,
+                    parameters,
+                    return_type,
+                },
+                acquires: BTreeMap::new(),
+                // bodies are not recovered, see write_ts for (FunctionName, &Function)
+                body: sp(self.loc, FunctionBody_::Native),
+            };
+            functions
+                .add(FunctionName(sp(self.loc, Symbol::from(name))), func)
+                .map_err(|_| anyhow!("Duplicate function {} in {}", name, self.module.self_id()))?;
+        }
+        Ok(functions)
+    }
+}
+
+/// Loads .mv files into an HLIR program with every module marked as non-source. Each file gets
+/// an empty entry in the returned source map so that diagnostics can point at it.
+pub fn program_from_bytecode(
+    paths: &[PathBuf],
+    config: &MoveToTsOptions,
+) -> anyhow::Result<(FilesSourceText, Program)> {
+    let address_names = parse_address_names(config)?;
+
+    let mut files = FilesSourceText::new();
+    let mut loaded = vec![];
+    for path in collect_bytecode_files(paths)?.iter() {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.to_string_lossy()))?;
+        let module = CompiledModule::deserialize(&bytes)
+            .map_err(|err| anyhow!("Invalid bytecode in {}: {}", path.to_string_lossy(), err))?;
+        let fname = path.to_string_lossy().to_string();
+        let file_hash = FileHash::new(&fname);
+        files.insert(file_hash, (Symbol::from(fname), String::new()));
+        loaded.push((module, Loc::new(file_hash, 0, 0)));
+    }
+    if loaded.is_empty() {
+        bail!("No .mv files found in the given bytecode paths");
+    }
+
+    let mut module_idents = BTreeMap::new();
+    for (module, loc) in loaded.iter() {
+        let module_id = module.self_id();
+        let name = address_names
+            .get(module_id.address())
+            .map(|name| sp(*loc, *name));
+        let address = NumericalAddress::new(module_id.address().into_bytes(), NumberFormat::Hex);
+        let mident = sp(
+            *loc,
+            ModuleIdent_ {
+                address: Address::Numerical(name, sp(*loc, address)),
+                module: ModuleName(sp(*loc, Symbol::from(module_id.name().as_str()))),
+            },
+        );
+        if module_idents.insert(module_id.clone(), mident).is_some() {
+            bail!("Bytecode for {} was given more than once", module_id);
+        }
+    }
+
+    let mut modules = UniqueMap::new();
+    for (dependency_order, (module, loc)) in loaded.iter().enumerate() {
+        let converter = Converter {
+            module,
+            module_idents: &module_idents,
+            loc: *loc,
+        };
+        let mdef = ModuleDefinition {
+            package_name: None,
+            attributes: UniqueMap::new(),
+            is_source_module: false,
+            dependency_order,
+            friends: UniqueMap::new(),
+            structs: converter.structs()?,
+            constants: UniqueMap::new(),
+            functions: converter.functions()?,
+        };
+        let mident = module_idents[&module.self_id()];
+        modules.add(mident, mdef).unwrap();
+    }
+
+    Ok((
+        files,
+        Program {
+            modules,
+            scripts: BTreeMap::new(),
+        },
+    ))
+}
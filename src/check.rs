@@ -0,0 +1,276 @@
+use crate::output::{read_output_manifest, stale_files};
+use crate::GeneratedFile;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Clone, Copy)]
+enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl DiffLine<'_> {
+    fn in_old(&self) -> bool {
+        !matches!(self, DiffLine::Added(_))
+    }
+
+    fn in_new(&self) -> bool {
+        !matches!(self, DiffLine::Removed(_))
+    }
+
+    fn is_change(&self) -> bool {
+        !matches!(self, DiffLine::Same(_))
+    }
+}
+
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    // regenerated files usually differ in a few places, so only run LCS on the part in between
+    // the common prefix and suffix
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    // lcs[i][j]: length of the longest common subsequence of old_mid[i..] and new_mid[j..]
+    let (n, m) = (old_mid.len(), new_mid.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = old[..prefix]
+        .iter()
+        .copied()
+        .map(DiffLine::Same)
+        .collect::<Vec<_>>();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old_mid[i] == new_mid[j] {
+            lines.push(DiffLine::Same(old_mid[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(DiffLine::Removed(old_mid[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new_mid[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old_mid[i..].iter().copied().map(DiffLine::Removed));
+    lines.extend(new_mid[j..].iter().copied().map(DiffLine::Added));
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .copied()
+            .map(DiffLine::Same),
+    );
+    lines
+}
+
+fn hunk_range<'a>(
+    lines: &[DiffLine<'a>],
+    start: usize,
+    end: usize,
+    in_side: impl Fn(&DiffLine<'a>) -> bool,
+) -> String {
+    let before = lines[..start].iter().filter(|line| in_side(line)).count();
+    let len = lines[start..end]
+        .iter()
+        .filter(|line| in_side(line))
+        .count();
+    // an empty range points at the line before it
+    let first = if len == 0 { before } else { before + 1 };
+    format!("{},{}", first, len)
+}
+
+/// Appends a diff line. Lines keep their newline, so that a missing one at the end of a file counts
+/// as a change, which is marked the way diff -u does.
+fn push_diff_line(out: &mut String, prefix: char, line: &str) {
+    out.push(prefix);
+    out.push_str(line);
+    if !line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// Renders a unified diff (as produced by diff -u) between two texts
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old_lines = old.split_inclusive('\n').collect::<Vec<_>>();
+    let new_lines = new.split_inclusive('\n').collect::<Vec<_>>();
+    let lines = diff_lines(&old_lines, &new_lines);
+    let changes = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.is_change())
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let mut idx = 0;
+    while idx < changes.len() {
+        // merge changes whose context would overlap into a single hunk
+        let mut last = idx;
+        while last + 1 < changes.len() && changes[last + 1] - changes[last] <= 2 * CONTEXT_LINES + 1
+        {
+            last += 1;
+        }
+        let start = changes[idx].saturating_sub(CONTEXT_LINES);
+        let end = (changes[last] + CONTEXT_LINES + 1).min(lines.len());
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(&lines, start, end, DiffLine::in_old),
+            hunk_range(&lines, start, end, DiffLine::in_new),
+        ));
+        for line in lines[start..end].iter() {
+            match line {
+                DiffLine::Same(text) => push_diff_line(&mut out, ' ', text),
+                DiffLine::Removed(text) => push_diff_line(&mut out, '-', text),
+                DiffLine::Added(text) => push_diff_line(&mut out, '+', text),
+            }
+        }
+        idx = last + 1;
+    }
+    out
+}
+
+/// Result of comparing generated files with the output directory
+#[derive(Debug, Default)]
+pub struct CheckOutcome {
+    /// paths of generated files that differ from the ones on disk, with a unified diff of each
+    pub diffs: Vec<(String, String)>,
+    /// generated files in the output directory that a run would remove
+    pub stale: Vec<String>,
+}
+
+impl CheckOutcome {
+    pub fn is_up_to_date(&self) -> bool {
+        self.diffs.is_empty() && self.stale.is_empty()
+    }
+}
+
+/// Compares generated files with what is currently in the output directory, collecting a diff for
+/// every file that differs and the generated files that would be removed
+pub fn check_outputs(root_path: &Path, files: &[GeneratedFile]) -> CheckOutcome {
+    let mut outcome = CheckOutcome::default();
+    for file in files.iter() {
+        let path = root_path.join(&file.path);
+        let existing = std::fs::read_to_string(&path).ok();
+        if existing.as_ref() == Some(&file.content) {
+            continue;
+        }
+        let name = file.path.to_string_lossy();
+        let old_name = if existing.is_some() {
+            format!("a/{}", name)
+        } else {
+            "/dev/null".to_string()
+        };
+        let diff = unified_diff(
+            existing.as_deref().unwrap_or(""),
+            &file.content,
+            &old_name,
+            &format!("b/{}", name),
+        );
+        outcome.diffs.push((name.to_string(), diff));
+    }
+
+    let generated = files
+        .iter()
+        .map(|file| file.path.to_string_lossy().to_string())
+        .collect::<BTreeSet<_>>();
+    let previous = read_output_manifest(root_path);
+    for relative_path in stale_files(&previous, |path| generated.contains(path)) {
+        if root_path.join(relative_path).is_file() {
+            outcome.stale.push(relative_path.clone());
+        }
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_texts_have_no_hunks() {
+        assert_eq!(
+            unified_diff("a\nb\n", "a\nb\n", "a/f", "b/f"),
+            "--- a/f\n+++ b/f\n"
+        );
+        assert_eq!(unified_diff("", "", "a/f", "b/f"), "--- a/f\n+++ b/f\n");
+    }
+
+    #[test]
+    fn new_file() {
+        assert_eq!(
+            unified_diff("", "a\nb\n", "/dev/null", "b/f"),
+            "--- /dev/null\n+++ b/f\n@@ -0,0 +1,2 @@\n+a\n+b\n"
+        );
+    }
+
+    #[test]
+    fn emptied_file() {
+        assert_eq!(
+            unified_diff("a\n", "", "a/f", "b/f"),
+            "--- a/f\n+++ b/f\n@@ -1,1 +0,0 @@\n-a\n"
+        );
+    }
+
+    #[test]
+    fn changed_line_with_context() {
+        assert_eq!(
+            unified_diff("1\n2\n3\n4\n5\n", "1\n2\nx\n4\n5\n", "a/f", "b/f"),
+            "--- a/f\n+++ b/f\n@@ -1,5 +1,5 @@\n 1\n 2\n-3\n+x\n 4\n 5\n"
+        );
+    }
+
+    #[test]
+    fn distant_changes_get_separate_hunks() {
+        let lines = |changed: &[usize]| {
+            (1..=20)
+                .map(|i| {
+                    if changed.contains(&i) {
+                        "x\n".to_string()
+                    } else {
+                        format!("{}\n", i)
+                    }
+                })
+                .collect::<String>()
+        };
+        let (old, new) = (lines(&[]), lines(&[2, 19]));
+        let diff = unified_diff(&old, &new, "a/f", "b/f");
+        assert_eq!(diff.matches("@@ -").count(), 2);
+        assert!(diff.contains("@@ -1,5 +1,5 @@\n"));
+        assert!(diff.contains("@@ -16,5 +16,5 @@\n"));
+    }
+
+    #[test]
+    fn missing_trailing_newline() {
+        assert_eq!(
+            unified_diff("a\nb", "a\nb\n", "a/f", "b/f"),
+            "--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n"
+        );
+        assert_eq!(
+            unified_diff("a\n", "a\nb", "a/f", "b/f"),
+            "--- a/f\n+++ b/f\n@@ -1,1 +1,2 @@\n a\n+b\n\\ No newline at end of file\n"
+        );
+    }
+}
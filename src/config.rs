@@ -0,0 +1,478 @@
+use crate::report::{MESSAGE_FORMAT_HUMAN, MESSAGE_FORMAT_VALUES};
+use crate::shared::MoveToTsOptions;
+use crate::utils::{MODULE_COMMONJS, MODULE_VALUES, NUMBERS_BIGINT, NUMBERS_BOXED, NUMBERS_VALUES};
+use anyhow::{anyhow, Context as _};
+use move_command_line_common::address::NumericalAddress;
+use move_core_types::account_address::AccountAddress;
+use move_package::BuildConfig;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Table in Move.toml holding generator options
+pub const MANIFEST_TABLE: &str = "move-to-ts";
+/// Sidecar file next to Move.toml; takes precedence over the Move.toml table
+pub const SIDECAR_FILENAME: &str = "move-to-ts.toml";
+
+/// Generator options as versioned with the Move package. Keys are the long command-line flags.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct FileOptions {
+    pub output_path: Option<PathBuf>,
+    pub test_address: Option<String>,
+    pub gen_cli: Option<bool>,
+    pub gen_ui: Option<bool>,
+    pub asynchronous: Option<bool>,
+    pub package_json_name: Option<String>,
+    pub package_version: Option<String>,
+    pub package_description: Option<String>,
+    pub dependency: Option<BTreeMap<String, String>>,
+    pub dev_dependency: Option<BTreeMap<String, String>>,
+    pub test_path_ignore_patterns: Option<Vec<String>>,
+    pub strict_compile: Option<bool>,
+    pub lenient: Option<bool>,
+    pub message_format: Option<String>,
+    pub module: Option<String>,
+    pub numbers: Option<String>,
+    pub prune: Option<bool>,
+    pub surface_only: Option<bool>,
+    pub source_map: Option<bool>,
+    pub named_addresses: Option<BTreeMap<String, String>>,
+    pub dev: Option<bool>,
+    pub test: Option<bool>,
+    pub skip_fetch_latest_git_deps: Option<bool>,
+    pub install_dir: Option<PathBuf>,
+    pub external_package: Option<BTreeMap<String, String>>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub no_default_excludes: Option<bool>,
+}
+
+/// Reads options from move-to-ts.toml, or from the [move-to-ts] table of Move.toml.
+/// Returns None if neither is present.
+pub fn load_file_options(package_root: &Path) -> anyhow::Result<Option<FileOptions>> {
+    let sidecar_path = package_root.join(SIDECAR_FILENAME);
+    if sidecar_path.is_file() {
+        let content = std::fs::read_to_string(&sidecar_path)?;
+        let options = toml::from_str(&content)
+            .with_context(|| format!("Invalid {}", sidecar_path.to_string_lossy()))?;
+        return Ok(Some(options));
+    }
+
+    let manifest_path = package_root.join("Move.toml");
+    let content = std::fs::read_to_string(&manifest_path)?;
+    let mut manifest: toml::value::Table = toml::from_str(&content)?;
+    match manifest.remove(MANIFEST_TABLE) {
+        None => Ok(None),
+        Some(table) => {
+            let options = table.try_into().with_context(|| {
+                format!(
+                    "Invalid [{}] table in {}",
+                    MANIFEST_TABLE,
+                    manifest_path.to_string_lossy()
+                )
+            })?;
+            Ok(Some(options))
+        }
+    }
+}
+
+/// Parses a NAME=ADDRESS command-line argument
+pub fn parse_address_mapping(mapping: &str) -> anyhow::Result<(&str, AccountAddress)> {
+    let (name, address) = mapping
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected NAME=ADDRESS, got {}", mapping))?;
+    let address = NumericalAddress::parse_str(address)
+        .map_err(|err| anyhow!("Invalid address in {}: {}", mapping, err))?;
+    Ok((name, address.into_inner()))
+}
+
+/// Parses a NAME=VERSION command-line argument, e.g. @types/node=^18.0.0
+pub fn parse_dependency_pin(pin: &str) -> anyhow::Result<(String, String)> {
+    match pin.split_once('=') {
+        Some((name, version)) if !name.is_empty() && !version.is_empty() => {
+            Ok((name.to_string(), version.to_string()))
+        }
+        _ => Err(anyhow!("Expected NAME=VERSION, got {}", pin)),
+    }
+}
+
+/// A Move package generated and published to npm separately. Its modules are imported from the
+/// npm module instead of being generated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternalPackage {
+    pub npm_module: String,
+    /// version requirement written to package.json
+    pub version: String,
+}
+
+/// Parses a PACKAGE=NPM_MODULE command-line argument. The npm module may carry a version, e.g.
+/// AptosFramework=@manahippo/aptos-framework-ts@^0.1.0
+pub fn parse_external_package(mapping: &str) -> anyhow::Result<(String, ExternalPackage)> {
+    let (name, spec) = mapping
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected PACKAGE=NPM_MODULE, got {}", mapping))?;
+    // an @ at the start belongs to the scope, not to the version
+    let (npm_module, version) = match spec.rfind('@') {
+        Some(idx) if idx > 0 => (&spec[..idx], &spec[idx + 1..]),
+        _ => (spec, "*"),
+    };
+    // a scoped module needs both the scope and the name: @scope/name
+    let valid_module = match npm_module.strip_prefix('@') {
+        Some(scoped) => match scoped.split_once('/') {
+            Some((scope, module)) => !scope.is_empty() && !module.is_empty(),
+            None => false,
+        },
+        None => !npm_module.is_empty(),
+    };
+    if name.is_empty() || !valid_module || version.is_empty() {
+        return Err(anyhow!("Expected PACKAGE=NPM_MODULE, got {}", mapping));
+    }
+    Ok((
+        name.to_string(),
+        ExternalPackage {
+            npm_module: npm_module.to_string(),
+            version: version.to_string(),
+        },
+    ))
+}
+
+fn parse_dependency_pins(args: &[String]) -> anyhow::Result<BTreeMap<String, String>> {
+    args.iter()
+        .flat_map(|arg| arg.split(','))
+        .map(parse_dependency_pin)
+        .collect()
+}
+
+/// An explicit --flag or --no-flag wins over the file
+fn merge_flag(flag: &mut bool, no_flag: bool, file: Option<bool>) {
+    if !*flag && !no_flag {
+        *flag = file.unwrap_or(false);
+    }
+}
+
+/// An explicit flag wins over the file. File values are checked even when overridden, so that a
+/// typo does not go unnoticed until the flag is dropped.
+fn merge_choice(
+    flag: &mut Option<String>,
+    file: Option<String>,
+    key: &str,
+    values: &[&str],
+) -> anyhow::Result<()> {
+    if let Some(value) = file {
+        if !values.contains(&value.as_str()) {
+            return Err(anyhow!(
+                "Invalid value \"{}\" for {}, expected one of: {}",
+                value,
+                key,
+                values.join(", ")
+            ));
+        }
+        if flag.is_none() {
+            *flag = Some(value);
+        }
+    }
+    Ok(())
+}
+
+impl MoveToTsOptions {
+    /// --module, defaulting to commonjs
+    pub fn module(&self) -> &str {
+        self.module.as_deref().unwrap_or(MODULE_COMMONJS)
+    }
+
+    /// --numbers, defaulting to boxed
+    pub fn numbers(&self) -> &str {
+        self.numbers.as_deref().unwrap_or(NUMBERS_BOXED)
+    }
+
+    /// --message-format, defaulting to human
+    pub fn message_format(&self) -> &str {
+        self.message_format
+            .as_deref()
+            .unwrap_or(MESSAGE_FORMAT_HUMAN)
+    }
+
+    /// Fills in values from the package's config file. Anything given on the command line wins;
+    /// boolean options set in the file are turned off with their --no- form.
+    pub fn merge_file_options(&mut self, file: FileOptions) -> anyhow::Result<()> {
+        if self.output_path.as_os_str().is_empty() {
+            if let Some(output_path) = file.output_path {
+                self.output_path = output_path;
+            }
+        }
+        if self.test_address.is_empty() {
+            if let Some(test_address) = file.test_address {
+                self.test_address = test_address;
+            }
+        }
+        if self.package_json_name.is_empty() {
+            if let Some(package_json_name) = file.package_json_name {
+                self.package_json_name = package_json_name;
+            }
+        }
+        if self.package_version.is_empty() {
+            if let Some(package_version) = file.package_version {
+                self.package_version = package_version;
+            }
+        }
+        if self.package_description.is_empty() {
+            if let Some(package_description) = file.package_description {
+                self.package_description = package_description;
+            }
+        }
+        merge_choice(
+            &mut self.message_format,
+            file.message_format,
+            "message-format",
+            MESSAGE_FORMAT_VALUES,
+        )?;
+        merge_choice(&mut self.module, file.module, "module", MODULE_VALUES)?;
+        merge_choice(&mut self.numbers, file.numbers, "numbers", NUMBERS_VALUES)?;
+        merge_flag(&mut self.cli, self.no_cli, file.gen_cli);
+        merge_flag(&mut self.ui, self.no_ui, file.gen_ui);
+        merge_flag(
+            &mut self.asynchronous,
+            self.no_asynchronous,
+            file.asynchronous,
+        );
+        merge_flag(
+            &mut self.strict_compile,
+            self.no_strict_compile,
+            file.strict_compile,
+        );
+        merge_flag(&mut self.lenient, self.no_lenient, file.lenient);
+        merge_flag(&mut self.source_map, self.no_source_map, file.source_map);
+        merge_flag(
+            &mut self.surface_only,
+            self.no_surface_only,
+            file.surface_only,
+        );
+        merge_flag(&mut self.prune, self.no_prune, file.prune);
+        merge_flag(&mut self.dev_mode, self.no_dev_mode, file.dev);
+        merge_flag(&mut self.test_mode, self.no_test_mode, file.test);
+        merge_flag(
+            &mut self.skip_fetch_latest_git_deps,
+            self.no_skip_fetch_latest_git_deps,
+            file.skip_fetch_latest_git_deps,
+        );
+        if self.install_dir.is_none() {
+            self.install_dir = file.install_dir;
+        }
+        // file addresses go first so that the command line overrides them
+        let mut named_addresses = file
+            .named_addresses
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, address)| format!("{}={}", name, address))
+            .collect::<Vec<_>>();
+        named_addresses.append(&mut self.named_addresses);
+        self.named_addresses = named_addresses;
+        // file mappings go first so that the command line overrides them
+        let mut external_package = file
+            .external_package
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, npm_module)| format!("{}={}", name, npm_module))
+            .collect::<Vec<_>>();
+        external_package.append(&mut self.external_package);
+        self.external_package = external_package;
+        // file pins go first so that the command line overrides them
+        let mut dependency = file
+            .dependency
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, version)| format!("{}={}", name, version))
+            .collect::<Vec<_>>();
+        dependency.append(&mut self.dependency);
+        self.dependency = dependency;
+        let mut dev_dependency = file
+            .dev_dependency
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, version)| format!("{}={}", name, version))
+            .collect::<Vec<_>>();
+        dev_dependency.append(&mut self.dev_dependency);
+        self.dev_dependency = dev_dependency;
+        if self.test_path_ignore_patterns.is_empty() {
+            self.test_path_ignore_patterns = file.test_path_ignore_patterns.unwrap_or_default();
+        }
+        // filters accumulate
+        self.include.extend(file.include.unwrap_or_default());
+        self.exclude.extend(file.exclude.unwrap_or_default());
+        merge_flag(
+            &mut self.no_default_excludes,
+            false,
+            file.no_default_excludes,
+        );
+        Ok(())
+    }
+
+    /// --external-package mappings, keyed by Move package name
+    pub fn external_packages(&self) -> anyhow::Result<BTreeMap<String, ExternalPackage>> {
+        let packages = self
+            .external_package
+            .iter()
+            .flat_map(|arg| arg.split(','))
+            .map(parse_external_package)
+            .collect::<anyhow::Result<BTreeMap<_, _>>>()?;
+        // the bigint parsers are installed on the one parser repo shared with the prebuilt
+        // packages, which would then get bigints where they expect U64 objects
+        if !packages.is_empty() && self.numbers() == NUMBERS_BIGINT {
+            return Err(anyhow!(
+                "--numbers bigint cannot be combined with --external-package, whose prebuilt \
+                 packages use boxed integers"
+            ));
+        }
+        Ok(packages)
+    }
+
+    /// --dependency pins, keyed by npm module. Later pins win.
+    pub fn dependency_pins(&self) -> anyhow::Result<BTreeMap<String, String>> {
+        parse_dependency_pins(&self.dependency)
+    }
+
+    /// --dev-dependency pins, keyed by npm module. Later pins win.
+    pub fn dev_dependency_pins(&self) -> anyhow::Result<BTreeMap<String, String>> {
+        parse_dependency_pins(&self.dev_dependency)
+    }
+
+    /// Build options used to resolve the package and its dependencies
+    pub fn build_config(&self) -> anyhow::Result<BuildConfig> {
+        let mut additional_named_addresses = BTreeMap::new();
+        for mapping in self.named_addresses.iter().flat_map(|arg| arg.split(',')) {
+            let (name, address) = parse_address_mapping(mapping)?;
+            additional_named_addresses.insert(name.to_string(), address);
+        }
+        Ok(BuildConfig {
+            dev_mode: self.dev_mode,
+            test_mode: self.test_mode,
+            install_dir: self.install_dir.clone(),
+            additional_named_addresses,
+            skip_fetch_latest_git_deps: self.skip_fetch_latest_git_deps,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn merged(args: &[&str], file: FileOptions) -> anyhow::Result<MoveToTsOptions> {
+        let mut options =
+            MoveToTsOptions::parse_from(std::iter::once("move-to-ts").chain(args.iter().copied()));
+        options.merge_file_options(file)?;
+        Ok(options)
+    }
+
+    #[test]
+    fn file_values_fill_in_defaults() {
+        let file = FileOptions {
+            module: Some("esm".to_string()),
+            numbers: Some("bigint".to_string()),
+            prune: Some(true),
+            ..Default::default()
+        };
+        let options = merged(&[], file).unwrap();
+        assert_eq!(options.module(), "esm");
+        assert_eq!(options.numbers(), "bigint");
+        assert!(options.prune);
+    }
+
+    #[test]
+    fn explicit_flags_win_over_file() {
+        let file = FileOptions {
+            module: Some("esm".to_string()),
+            numbers: Some("bigint".to_string()),
+            prune: Some(true),
+            lenient: Some(true),
+            ..Default::default()
+        };
+        let options = merged(
+            &["--module", "commonjs", "--numbers", "boxed", "--no-prune"],
+            file,
+        )
+        .unwrap();
+        assert_eq!(options.module(), "commonjs");
+        assert_eq!(options.numbers(), "boxed");
+        assert!(!options.prune);
+        assert!(options.lenient);
+    }
+
+    #[test]
+    fn last_of_flag_and_no_flag_wins() {
+        let file = FileOptions {
+            prune: Some(true),
+            ..Default::default()
+        };
+        assert!(!merged(&["--prune", "--no-prune"], file).unwrap().prune);
+        assert!(
+            merged(&["--no-prune", "--prune"], FileOptions::default())
+                .unwrap()
+                .prune
+        );
+    }
+
+    #[test]
+    fn invalid_file_values_are_rejected() {
+        let file = FileOptions {
+            numbers: Some("BigInt".to_string()),
+            ..Default::default()
+        };
+        assert!(merged(&[], file).is_err());
+        let file = FileOptions {
+            module: Some("es6".to_string()),
+            ..Default::default()
+        };
+        assert!(merged(&["--module", "esm"], file).is_err());
+    }
+
+    #[test]
+    fn external_package_with_and_without_version() {
+        let (name, package) = parse_external_package("AptosFramework=aptos-framework-ts").unwrap();
+        assert_eq!(name, "AptosFramework");
+        assert_eq!(package.npm_module, "aptos-framework-ts");
+        assert_eq!(package.version, "*");
+        let (_, package) =
+            parse_external_package("AptosFramework=@manahippo/aptos-framework-ts@^0.1.0").unwrap();
+        assert_eq!(package.npm_module, "@manahippo/aptos-framework-ts");
+        assert_eq!(package.version, "^0.1.0");
+        let (_, package) =
+            parse_external_package("AptosFramework=@manahippo/aptos-framework-ts").unwrap();
+        assert_eq!(package.npm_module, "@manahippo/aptos-framework-ts");
+        assert_eq!(package.version, "*");
+    }
+
+    #[test]
+    fn external_packages_need_boxed_numbers() {
+        let args = ["--external-package", "AptosFramework=aptos-framework-ts"];
+        let options = merged(&args, FileOptions::default()).unwrap();
+        assert_eq!(options.external_packages().unwrap().len(), 1);
+        let file = FileOptions {
+            numbers: Some("bigint".to_string()),
+            ..Default::default()
+        };
+        assert!(merged(&args, file).unwrap().external_packages().is_err());
+        // nothing to conflict with
+        let options = merged(&["--numbers", "bigint"], FileOptions::default()).unwrap();
+        assert!(options.external_packages().unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_external_packages_are_rejected() {
+        for mapping in [
+            "AptosFramework",
+            "=aptos-framework-ts",
+            "AptosFramework=",
+            "AptosFramework=@",
+            "AptosFramework=aptos-framework-ts@",
+            "AptosFramework=@manahippo/aptos-framework-ts@",
+            "AptosFramework=@manahippo",
+            "AptosFramework=@manahippo/",
+        ] {
+            assert!(parse_external_package(mapping).is_err(), "{}", mapping);
+        }
+    }
+}
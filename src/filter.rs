@@ -0,0 +1,107 @@
+use crate::shared::{format_address, format_address_hex, MoveToTsOptions};
+use move_compiler::expansion::ast::ModuleIdent;
+
+/// Framework modules whose natives have no TypeScript implementation. Excluded unless matched by
+/// an --include pattern, or --no-default-excludes is given.
+pub const DEFAULT_EXCLUDES: &[&str] = &["0x1::*secp256k1*", "0x1::ristretto255*"];
+
+/// Matches `text` against a glob pattern supporting `*` (any run of characters) and `?` (any
+/// single character)
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    // position of the last '*' seen, and the text position it is currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Patterns containing `::` are matched against `address::module`, with the address either in hex
+/// (0x1::coin) or named (aptos_framework::coin) form. Other patterns are matched against the
+/// package name (AptosFramework).
+fn pattern_matches(pattern: &str, mident: &ModuleIdent, package_name: Option<&str>) -> bool {
+    if pattern.contains("::") {
+        let module = mident.value.module;
+        let hex_name = format!("{}::{}", format_address_hex(mident.value.address), module);
+        let named_name = format!("{}::{}", format_address(mident.value.address), module);
+        glob_match(pattern, &hex_name) || glob_match(pattern, &named_name)
+    } else {
+        package_name.map_or(false, |name| glob_match(pattern, name))
+    }
+}
+
+/// Whether a module should be translated, according to --include and --exclude
+pub fn is_module_included(
+    config: &MoveToTsOptions,
+    mident: &ModuleIdent,
+    package_name: Option<&str>,
+) -> bool {
+    let explicitly_included = config
+        .include
+        .iter()
+        .any(|pattern| pattern_matches(pattern, mident, package_name));
+    let excluded = config
+        .exclude
+        .iter()
+        .any(|pattern| pattern_matches(pattern, mident, package_name));
+    let excluded_by_default = !config.no_default_excludes
+        && !explicitly_included
+        && DEFAULT_EXCLUDES
+            .iter()
+            .any(|pattern| pattern_matches(pattern, mident, package_name));
+    (config.include.is_empty() || explicitly_included) && !excluded && !excluded_by_default
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_matches_across_separators() {
+        assert!(glob_match("0x1::*", "0x1::coin"));
+        assert!(glob_match("*::coin", "aptos_framework::coin"));
+        assert!(glob_match("0x1*coin", "0x1::managed_coin"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("0x1::*", "0x2::coin"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(glob_match("0x?::coin", "0x1::coin"));
+        assert!(!glob_match("0x?::coin", "0x10::coin"));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn backtracks_after_partial_match() {
+        assert!(glob_match("*ab", "aab"));
+        assert!(glob_match("a*b*c", "abbbc"));
+        assert!(!glob_match("a*b", "abc"));
+    }
+
+    #[test]
+    fn default_excludes_secp256k1_and_ristretto() {
+        let matches = |text| DEFAULT_EXCLUDES.iter().any(|p| glob_match(p, text));
+        assert!(matches("0x1::secp256k1"));
+        assert!(matches("0x1::ed25519_secp256k1_ext"));
+        assert!(matches("0x1::ristretto255"));
+        assert!(matches("0x1::ristretto255_bulletproofs"));
+        assert!(!matches("0x1::ed25519"));
+        assert!(!matches("0x2::secp256k1"));
+    }
+}
@@ -1,6 +1,7 @@
+use crate::ast_exp::bigint_width;
 use crate::ast_to_ts::is_type_signer;
 use crate::shared::*;
-use crate::utils::{capitalize, rename};
+use crate::utils::{capitalize, import_specifier, rename};
 use itertools::Itertools;
 use move_compiler::diagnostics::{Diagnostic, Diagnostics};
 use move_compiler::expansion::ast::ModuleIdent;
@@ -19,16 +20,25 @@ pub fn check_allowed_structs_for_entry_function(
     mi: &ModuleIdent,
     sname: &StructName,
     loc: Loc,
+    bigint: bool,
 ) -> TermResult {
     let address = format_address_hex(mi.value.address);
 
     let short_name = format!("{}::{}::{}", address, mi.value.module, sname);
 
     if short_name == "0x1::string::String" {
-        Ok(format!(
+        let string = format!(
             "new ActualStringClass({{bytes: strToU8({})}}, parseTypeTagOrThrow('0x1::string::String'))",
             name
-        ))
+        );
+        if bigint {
+            Ok(format!(
+                "unboxed({}, parseTypeTagOrThrow('0x1::string::String'))",
+                string
+            ))
+        } else {
+            Ok(string)
+        }
     } else {
         derr!((
             loc,
@@ -37,7 +47,7 @@ pub fn check_allowed_structs_for_entry_function(
     }
 }
 
-pub fn vector_type_ts_parser(name: &String, element_type: &BaseType) -> TermResult {
+pub fn vector_type_ts_parser(name: &String, element_type: &BaseType, bigint: bool) -> TermResult {
     match &element_type.value {
         BaseType_::Param(_tparam) => {
             // FIXME
@@ -48,9 +58,12 @@ pub fn vector_type_ts_parser(name: &String, element_type: &BaseType) -> TermResu
         }
         BaseType_::Apply(_, typename, _) => match &typename.value {
             TypeName_::ModuleType(mi, sname) => {
-                check_allowed_structs_for_entry_function(name, mi, sname, element_type.loc)
+                check_allowed_structs_for_entry_function(name, mi, sname, element_type.loc, bigint)
             }
             TypeName_::Builtin(builtin) => match &builtin.value {
+                BuiltinTypeName_::U8 if bigint => {
+                    Ok(format!("strToU8({}).map(u => u.toBigInt())", name))
+                }
                 BuiltinTypeName_::U8 => Ok(format!("strToU8({})", name)),
                 _ => derr!((
                     element_type.loc,
@@ -62,7 +75,7 @@ pub fn vector_type_ts_parser(name: &String, element_type: &BaseType) -> TermResu
     }
 }
 
-pub fn stype_to_ts_parser(name: &String, loc: Loc, stype: &SingleType) -> TermResult {
+pub fn stype_to_ts_parser(name: &String, loc: Loc, stype: &SingleType, bigint: bool) -> TermResult {
     let base = match &stype.value {
         SingleType_::Base(b) => b,
         SingleType_::Ref(_, b) => b,
@@ -77,18 +90,33 @@ pub fn stype_to_ts_parser(name: &String, loc: Loc, stype: &SingleType) -> TermRe
         }
         BaseType_::Apply(_, typename, targs) => match &typename.value {
             TypeName_::ModuleType(mi, sname) => {
-                check_allowed_structs_for_entry_function(name, mi, sname, stype.loc)
+                check_allowed_structs_for_entry_function(name, mi, sname, stype.loc, bigint)
             }
             TypeName_::Builtin(builtin) => match &builtin.value {
+                // range-checked like the boxed integers
+                BuiltinTypeName_::U8
+                | BuiltinTypeName_::U16
+                | BuiltinTypeName_::U32
+                | BuiltinTypeName_::U64
+                | BuiltinTypeName_::U128
+                | BuiltinTypeName_::U256
+                    if bigint =>
+                {
+                    let width = bigint_width(&builtin.value).unwrap();
+                    Ok(format!("check{}(BigInt({}))", width, name))
+                }
                 BuiltinTypeName_::U8 => Ok(format!("u8({})", name)),
+                BuiltinTypeName_::U16 => Ok(format!("u16({})", name)),
+                BuiltinTypeName_::U32 => Ok(format!("u32({})", name)),
                 BuiltinTypeName_::U64 => Ok(format!("u64({})", name)),
                 BuiltinTypeName_::U128 => Ok(format!("u128({})", name)),
+                BuiltinTypeName_::U256 => Ok(format!("u256({})", name)),
                 BuiltinTypeName_::Bool => Ok(format!("{}=='true'", name)),
                 BuiltinTypeName_::Address => Ok(format!("new HexString({})", name)),
                 BuiltinTypeName_::Signer => unreachable!(),
                 BuiltinTypeName_::Vector => {
                     assert!(targs.len() == 1);
-                    vector_type_ts_parser(name, &targs[0])
+                    vector_type_ts_parser(name, &targs[0], bigint)
                 }
             },
         },
@@ -131,7 +159,7 @@ pub fn format_qualified_fname_and_import(
 }
 
 /// Generates module_name:func-name command for entry functions marked with #[cmd]
-pub fn generate_command(cmd: &CmdParams) -> Result<(String, String), Diagnostic> {
+pub fn generate_command(cmd: &CmdParams, bigint: bool) -> Result<(String, String), Diagnostic> {
     let type_param_names = cmd
         .func
         .signature
@@ -170,7 +198,7 @@ pub fn generate_command(cmd: &CmdParams) -> Result<(String, String), Diagnostic>
         param_parsers.push(format!(
             "  const {}_ = {};",
             pname,
-            stype_to_ts_parser(&pname.to_string(), pname.0.loc, ptype)?
+            stype_to_ts_parser(&pname.to_string(), pname.0.loc, ptype, bigint)?
         ));
         arguments.push(format!("  .argument('<{}>')", pname));
     }
@@ -260,6 +288,7 @@ pub fn generate_printer(
     sdef: &StructDefinition,
     fname: &Name,
     fsig: &FunctionSignature,
+    bigint: bool,
 ) -> Result<(String, String), Diagnostic> {
     let mut arg_decls = vec![];
     for tp in sdef.type_parameters.iter() {
@@ -291,7 +320,12 @@ pub fn generate_printer(
 
     let mut param_handlers = vec![];
     for (name, ty) in fsig.parameters[1..].iter() {
-        param_handlers.push(stype_to_ts_parser(&name.to_string(), name.0.loc, ty)?);
+        param_handlers.push(stype_to_ts_parser(
+            &name.to_string(),
+            name.0.loc,
+            ty,
+            bigint,
+        )?);
     }
 
     let cmd_func_name = format!("{}_{}", sname, fname);
@@ -328,7 +362,10 @@ program
 }
 
 /// Generate query commands for functions marked with #[query]
-pub fn generate_query_printer(query: &CmdParams) -> Result<(String, String), Diagnostic> {
+pub fn generate_query_printer(
+    query: &CmdParams,
+    bigint: bool,
+) -> Result<(String, String), Diagnostic> {
     let mut arg_decls = vec![];
     for tp in query.func.signature.type_parameters.iter() {
         arg_decls.push(format!("{}: string", tp.user_specified_name));
@@ -365,7 +402,12 @@ pub fn generate_query_printer(query: &CmdParams) -> Result<(String, String), Dia
 
     let mut param_handlers = vec![];
     for (name, ty) in params_no_signer {
-        param_handlers.push(stype_to_ts_parser(&name.to_string(), name.0.loc, ty)?);
+        param_handlers.push(stype_to_ts_parser(
+            &name.to_string(),
+            name.0.loc,
+            ty,
+            bigint,
+        )?);
     }
 
     let cmd_func_name = format!("{}_{}", query.mi.value.module, query.fname);
@@ -474,12 +516,14 @@ program
     (body, package_name)
 }
 
-pub fn generate_cli(ctx: &Context) -> Result<(String, String), Diagnostics> {
+pub fn generate_cli(ctx: &mut Context) -> Result<(String, String), Diagnostics> {
+    let mut warnings = Diagnostics::new();
     let mut commands = vec![];
     let mut printers = vec![];
     let mut imported_packages = BTreeSet::new();
+    let bigint = ctx.is_bigint();
     for cmd_param in ctx.cmds.iter() {
-        let command_res = generate_command(cmd_param);
+        let command_res = generate_command(cmd_param, bigint);
         if let Ok((cmd_str, package_name)) = command_res {
             commands.push(cmd_str);
             imported_packages.insert(package_name);
@@ -494,20 +538,28 @@ pub fn generate_cli(ctx: &Context) -> Result<(String, String), Diagnostics> {
         let (mi, sname, sdef, fname, fsig) = method;
         // if sdef is a resource type, generate printer for it
         if sdef.abilities.has_ability_(Ability_::Key) {
-            let printer_res = generate_printer(mi, sname, sdef, fname, fsig);
+            let printer_res = generate_printer(mi, sname, sdef, fname, fsig, bigint);
             if let Ok((printer_body, package_name)) = printer_res {
                 printers.push(printer_body);
                 imported_packages.insert(package_name);
             } else {
-                println!(
-                    "Skipping cmd_printer generation for {} as it contains unsupported arguments",
-                    fname
-                );
+                warnings.add(Diagnostic::new(
+                    SkippedGeneration {},
+                    (
+                        fname.loc,
+                        format!(
+                            "Skipping cmd_printer generation for {} as it contains unsupported arguments",
+                            fname
+                        ),
+                    ),
+                    std::iter::empty::<(Loc, String)>(),
+                    std::iter::empty::<String>(),
+                ));
             }
         }
     }
     for query in ctx.queries.iter() {
-        let command_res = generate_query_printer(query);
+        let command_res = generate_query_printer(query, bigint);
         if let Ok((cmd_str, package_name)) = command_res {
             commands.push(cmd_str);
             imported_packages.insert(package_name);
@@ -524,17 +576,24 @@ pub fn generate_cli(ctx: &Context) -> Result<(String, String), Diagnostics> {
         printers.push(printer_body);
         imported_packages.insert(package_name);
     }
+    ctx.warnings.extend(warnings);
     let package_imports = imported_packages
         .iter()
-        .map(|name| format!("import * as {} from './{}';", capitalize(name), name))
+        .map(|name| {
+            format!(
+                "import * as {} from '{}';",
+                capitalize(name),
+                import_specifier(&format!("./{}", name), true, ctx.config.module())
+            )
+        })
         .join("\n");
     let filename = "cli.ts".to_string();
     let content = format!(
         r###"
-import {{ AptosParserRepo, getTypeTagFullname, StructTag, parseTypeTagOrThrow, u8, u64, u128, print, strToU8, u8str, DummyCache, ActualStringClass, sendPayloadTx, getSimulationKeys }} from "@manahippo/move-to-ts";
+import {{ AptosParserRepo, getTypeTagFullname, StructTag, parseTypeTagOrThrow, u8, u16, u32, u64, u128, u256, checkU8, checkU16, checkU32, checkU64, checkU128, checkU256, unboxed, print, strToU8, u8str, DummyCache, ActualStringClass, sendPayloadTx, getSimulationKeys }} from "@manahippo/move-to-ts";
 import {{ AptosAccount, AptosClient, HexString, Types }} from "aptos";
 import {{ Command }} from "commander";
-import {{ getProjectRepo }} from "./";
+import {{ getProjectRepo }} from "{}";
 import * as fs from "fs";
 import * as yaml from "yaml";
 {}
@@ -579,6 +638,7 @@ program
 
 program.parse();
 "###,
+        import_specifier("./", true, ctx.config.module()),
         package_imports,
         commands.join("\n"),
         printers.join("\n"),
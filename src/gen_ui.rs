@@ -29,13 +29,15 @@ pub fn base_type_to_typetag_builder(base_ty: &BaseType, tparams: &Vec<TParam>) -
                 }
                 BuiltinTypeName_::Bool => Ok("AtomicTypeTag.Bool".to_string()),
                 BuiltinTypeName_::U8 => Ok("AtomicTypeTag.U8".to_string()),
+                BuiltinTypeName_::U16 => Ok("AtomicTypeTag.U16".to_string()),
+                BuiltinTypeName_::U32 => Ok("AtomicTypeTag.U32".to_string()),
                 BuiltinTypeName_::U64 => Ok("AtomicTypeTag.U64".to_string()),
                 BuiltinTypeName_::U128 => Ok("AtomicTypeTag.U128".to_string()),
+                BuiltinTypeName_::U256 => Ok("AtomicTypeTag.U256".to_string()),
                 BuiltinTypeName_::Address => Ok("AtomicTypeTag.Address".to_string()),
                 BuiltinTypeName_::Signer => Ok("AtomicTypeTag.Signer".to_string()),
             },
             TypeName_::ModuleType(mident, sname) => {
-                let address = format_address_hex(mident.value.address);
                 let modname = mident.value.module;
                 let mut tparam_parts = vec![];
                 for base in ss.iter() {
@@ -43,8 +45,8 @@ pub fn base_type_to_typetag_builder(base_ty: &BaseType, tparams: &Vec<TParam>) -
                 }
                 let tparams = format!("[{}]", tparam_parts.join(", "));
                 Ok(format!(
-                    "new StructTag(new HexString({}), {}, {}, {})",
-                    quote(&address),
+                    "new StructTag({}, {}, {}, {})",
+                    ts_address(mident.value.address),
                     quote(&modname),
                     quote(&sname),
                     tparams
@@ -77,7 +79,7 @@ pub fn write_cmd_arg(
     Ok(())
 }
 
-pub fn write_command(cmd: &CmdParams, w: &mut TsgenWriter) -> TermResult {
+pub fn write_command(cmd: &CmdParams, w: &mut TsgenWriter, bigint: bool) -> TermResult {
     w.writeln("{");
     w.increase_indent();
 
@@ -143,7 +145,7 @@ pub fn write_command(cmd: &CmdParams, w: &mut TsgenWriter) -> TermResult {
         w.writeln(format!(
             "const {} = {};",
             pname,
-            stype_to_ts_parser(&format!("{}_", pname), pname.0.loc, ptype)?
+            stype_to_ts_parser(&format!("{}_", pname), pname.0.loc, ptype, bigint)?
         ));
     }
     let (payload_builder, package_name) =
@@ -183,6 +185,7 @@ pub fn write_module(
     cmds: &[&CmdParams],
     all_imported_packages: &mut BTreeSet<String>,
     w: &mut TsgenWriter,
+    bigint: bool,
 ) -> WriteResult {
     w.writeln("{");
     w.increase_indent();
@@ -193,7 +196,7 @@ pub fn write_module(
     w.increase_indent();
 
     for cmd in cmds.iter() {
-        all_imported_packages.insert(write_command(cmd, w)?);
+        all_imported_packages.insert(write_command(cmd, w, bigint)?);
     }
 
     w.decrease_indent();
@@ -210,6 +213,7 @@ pub fn write_package(
     module_cmds: &[(&String, &Vec<&CmdParams>)],
     all_imported_packages: &mut BTreeSet<String>,
     w: &mut TsgenWriter,
+    bigint: bool,
 ) -> WriteResult {
     w.writeln("{");
     w.increase_indent();
@@ -218,7 +222,7 @@ pub fn write_package(
     w.writeln("modules: [");
     w.increase_indent();
     for (module, cmds) in module_cmds.iter() {
-        write_module(name, module, cmds, all_imported_packages, w)?;
+        write_module(name, module, cmds, all_imported_packages, w, bigint)?;
     }
     w.decrease_indent();
     w.writeln("],");
@@ -236,10 +240,11 @@ pub fn generate_ui(ctx: &mut Context) -> Result<Vec<(String, String)>, Diagnosti
 import React, { useState } from 'react';
 import { Input } from 'semantic-ui-react';
 import ReactDOM from 'react-dom/client';
-import { AptosParserRepo, getTypeTagFullname, StructTag, TypeTag, AtomicTypeTag, VectorTag, parseTypeTagOrThrow, u8, u64, u128, print, strToU8, u8str, DummyCache }
+import { AptosParserRepo, getTypeTagFullname, StructTag, TypeTag, AtomicTypeTag, VectorTag, parseTypeTagOrThrow, u8, u16, u32, u64, u128, u256, checkU8, checkU16, checkU32, checkU64, checkU128, checkU256, unboxed, print, strToU8, u8str, DummyCache }
 from
 "@manahippo/move-to-ts";
 import './index.css';
+import * as $addresses from './addresses';
 import { AptosAccount, AptosClient, HexString, Types } from "aptos";
 
 interface IArg {
@@ -436,6 +441,7 @@ const App = () => {
             &package_modules,
             &mut all_imported_packages,
             &mut writer,
+            ctx.is_bigint(),
         ) {
             let mut diags = Diagnostics::new();
             diags.add(diag);
@@ -0,0 +1,585 @@
+mod ast_exp;
+mod ast_tests;
+pub mod ast_to_ts;
+pub mod bytecode;
+pub mod check;
+pub mod config;
+pub mod filter;
+pub mod gen_cli;
+pub mod gen_ui;
+pub mod manifest;
+pub mod output;
+mod parallel;
+mod prune;
+pub mod report;
+pub mod shared;
+pub mod source_map;
+pub mod tsgen_writer;
+pub mod utils;
+
+use crate::filter::is_module_included;
+use crate::gen_cli::generate_cli;
+use crate::gen_ui::{gen_public_html, generate_ui};
+use crate::output::JsonMerger;
+use crate::parallel::ModuleOutput;
+use crate::prune::prune_program;
+use crate::report::{report_diagnostics, report_message};
+use crate::shared::{format_address, is_same_package};
+use crate::source_map::generate_source_map;
+use crate::utils::{generate_index, generate_topmost_index};
+use move_command_line_common::address::NumericalAddress;
+use move_command_line_common::parser::NumberFormat;
+use move_compiler::diagnostics::{Diagnostics, FilesSourceText};
+use move_compiler::hlir::ast::Program;
+use move_compiler::shared::PackagePaths;
+use move_compiler::*;
+use move_package::compilation::package_layout::CompiledPackageLayout;
+use move_package::resolution::resolution_graph::ResolvedGraph;
+use move_symbol_pool::Symbol;
+use shared::{Context, MoveToTsOptions};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A generated file, with a path relative to the output root (see `output_root`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratedFile {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+impl GeneratedFile {
+    pub fn new(subdir: &str, pair: (String, String)) -> Self {
+        let (filename, content) = pair;
+        Self {
+            path: Path::new(subdir).join(filename),
+            content,
+        }
+    }
+}
+
+/// Result of a successful run
+pub struct Generated {
+    pub files: Vec<GeneratedFile>,
+    /// compiler warnings (--strict-compile), parts of the cli that had to be skipped, and in
+    /// --lenient mode everything that got stubbed or skipped
+    pub warnings: Diagnostics,
+    /// source files, needed to render `warnings`
+    pub sources: FilesSourceText,
+}
+
+impl Generated {
+    /// Prints the warnings in the format selected by --message-format, leaving none behind
+    pub fn report_warnings(&mut self, config: &MoveToTsOptions) {
+        let warnings = std::mem::replace(&mut self.warnings, Diagnostics::new());
+        report_diagnostics(&self.sources, warnings, config);
+    }
+}
+
+pub enum GenerateError {
+    /// the package could not be resolved, or its sources could not be read
+    Package(anyhow::Error),
+    /// the compiler or the translator rejected the package. Comes with the source files needed to
+    /// render the diagnostics.
+    Diagnostics(FilesSourceText, Diagnostics),
+}
+
+impl GenerateError {
+    /// Prints the error in the format selected by --message-format
+    pub fn report(self, config: &MoveToTsOptions) {
+        match self {
+            GenerateError::Package(err) => report_message(&format!("{:#}", err), config),
+            GenerateError::Diagnostics(files, diags) => report_diagnostics(&files, diags, config),
+        }
+    }
+}
+
+fn check_diagnostics<T>(
+    files: &FilesSourceText,
+    res: Result<T, Diagnostics>,
+) -> Result<T, GenerateError> {
+    res.map_err(|diags| GenerateError::Diagnostics(files.clone(), diags))
+}
+
+/// Like `check_diagnostics`, but in --lenient mode a failed step is recorded and skipped (None)
+/// instead of failing the whole run
+fn recover_diagnostics<T>(
+    files: &FilesSourceText,
+    config: &MoveToTsOptions,
+    recovered: &mut Diagnostics,
+    res: Result<T, Diagnostics>,
+) -> Result<Option<T>, GenerateError> {
+    match res {
+        Ok(val) => Ok(Some(val)),
+        Err(diags) if config.lenient => {
+            recovered.extend(diags);
+            Ok(None)
+        }
+        Err(diags) => Err(GenerateError::Diagnostics(files.clone(), diags)),
+    }
+}
+
+/// Directory generated files are relative to: --output-path if given, otherwise
+/// build/typescript under the package (or install dir)
+pub fn output_root(resolution_graph: &ResolvedGraph, config: &MoveToTsOptions) -> PathBuf {
+    if !config.output_path.as_os_str().is_empty() {
+        return config.output_path.clone();
+    }
+    let project_root = match &resolution_graph.build_options.install_dir {
+        Some(under_path) => under_path.clone(),
+        None => resolution_graph.root_package_path.clone(),
+    };
+    project_root
+        .join(CompiledPackageLayout::Root.path())
+        .join("typescript")
+}
+
+/// Output root of --bytecode runs, which have no package: --output-path if given, otherwise
+/// build/typescript
+pub fn bytecode_output_root(config: &MoveToTsOptions) -> PathBuf {
+    if config.output_path.as_os_str().is_empty() {
+        Path::new("build").join("typescript")
+    } else {
+        config.output_path.clone()
+    }
+}
+
+/// Directories compiled for a package of the resolution graph: sources/, plus tests/ of
+/// dependencies when tests are generated
+fn package_dirs(package_path: &Path, is_root: bool, config: &MoveToTsOptions) -> Vec<PathBuf> {
+    let mut dirs = vec![package_path.join("sources")];
+    let tests_dir = package_path.join("tests");
+    if !is_root && !config.test_address.is_empty() && tests_dir.is_dir() {
+        dirs.push(tests_dir);
+    }
+    dirs
+}
+
+fn dir_strings(dirs: Vec<PathBuf>) -> Vec<String> {
+    dirs.into_iter()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Every directory `generate_from_graph` compiles, i.e. whose changes affect the output
+pub fn source_dirs(resolution_graph: &ResolvedGraph, config: &MoveToTsOptions) -> Vec<PathBuf> {
+    let root_name = resolution_graph.root_package.package.name;
+    resolution_graph
+        .package_table
+        .iter()
+        .flat_map(|(name, package)| package_dirs(&package.package_path, *name == root_name, config))
+        .collect()
+}
+
+/// Resolves the package at `package_path` and generates its TypeScript SDK without touching disk
+pub fn generate(package_path: &Path, config: &MoveToTsOptions) -> Result<Generated, GenerateError> {
+    let resolution_graph = config
+        .build_config()
+        .map_err(GenerateError::Package)?
+        .resolution_graph_for_package(package_path)
+        .map_err(GenerateError::Package)?;
+    generate_from_graph(&resolution_graph, config)
+}
+
+/// Same as `generate`, for callers that already hold a resolution graph (e.g. watch mode)
+pub fn generate_from_graph(
+    resolution_graph: &ResolvedGraph,
+    config: &MoveToTsOptions,
+) -> Result<Generated, GenerateError> {
+    /*
+    1. Go through the compilation pipeline to report diagnostics if any, otherwise retain AST
+       from the typing stage
+    2. feed typing AST through move-tsgen to get files
+    3. collect files
+    4. collect jest .test.ts files if --test is given
+    5. collect cli and ui if --gen-cli and --gen-tui is given
+    6. collect package.json and tsconfig.json if --generate-package is given
+    7. generate various index.ts for packages
+     */
+    let root_package = &resolution_graph.package_table[&resolution_graph.root_package.package.name];
+
+    // 1
+    let mut named_address_mapping = BTreeMap::new();
+
+    resolution_graph
+        .extract_named_address_mapping()
+        .for_each(|(name, addr)| {
+            named_address_mapping.insert(
+                name,
+                NumericalAddress::new(addr.into_bytes(), NumberFormat::Hex),
+            );
+        });
+
+    let mut dependencies = resolution_graph
+        .package_table
+        .iter()
+        .filter_map(|(name, package)| {
+            if name == &root_package.source_package.package.name {
+                None
+            } else {
+                let path = PackagePaths {
+                    name: Some(*name),
+                    paths: dir_strings(package_dirs(&package.package_path, false, config)),
+                    named_address_map: named_address_mapping.clone(),
+                };
+                Some(path)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut source_package_paths = vec![PackagePaths {
+        name: Some(root_package.source_package.package.name),
+        paths: dir_strings(package_dirs(&root_package.package_path, true, config)),
+        named_address_map: named_address_mapping,
+    }];
+
+    source_package_paths.append(&mut dependencies);
+
+    let flags = if !config.test_address.is_empty() {
+        Flags::testing()
+    } else {
+        Flags::empty()
+    };
+
+    // mark everything as source to avoid all functions in dependencies being marked as "native"
+    let compiler = Compiler::from_package_paths(source_package_paths, vec![]).set_flags(flags);
+
+    let (files, res_comments_compiler) = compiler
+        .run::<{ move_compiler::PASS_HLIR }>()
+        .map_err(GenerateError::Package)?;
+
+    let (_comments, hlir_compiler) = check_diagnostics(&files, res_comments_compiler)?;
+
+    let (empty_compiler, hlir_program) = hlir_compiler.into_ast();
+
+    // run the rest of the pipeline (borrow checking, bytecode generation) on the HLIR we already
+    // have, so that errors are reported before anything gets generated
+    let compile_warnings = if config.strict_compile {
+        let full_res = empty_compiler
+            .at_hlir(hlir_program.clone())
+            .run::<{ move_compiler::PASS_COMPILATION }>();
+        let full_compiler = check_diagnostics(&files, full_res)?;
+        let (_units, warnings) = full_compiler.into_compiled_units();
+        Some(warnings)
+    } else {
+        None
+    };
+
+    let root = output_root(resolution_graph, config);
+    let root_package_name = root_package.source_package.package.name;
+    let mut generated =
+        generate_from_program(&files, hlir_program, config, &root, Some(root_package_name))?;
+    if let Some(warnings) = compile_warnings {
+        generated.warnings.extend(warnings);
+    }
+    Ok(generated)
+}
+
+/// Generates TypeScript for compiled bytecode modules (.mv files). Function bodies are not
+/// recovered: every function throws when called, but structs, parsers, resource loaders and
+/// payload builders are generated from the bytecode signatures.
+pub fn generate_from_bytecode(
+    paths: &[PathBuf],
+    config: &MoveToTsOptions,
+) -> Result<Generated, GenerateError> {
+    let (files, hlir_program) =
+        bytecode::program_from_bytecode(paths, config).map_err(GenerateError::Package)?;
+    let root = bytecode_output_root(config);
+    generate_from_program(&files, hlir_program, config, &root, None)
+}
+
+/// Steps 2-7 of `generate_from_graph`, on a program that is known to compile. Source maps, and
+/// package files merged into the existing ones, need to know the `output_root` they go to.
+/// --prune starts from the modules of `root_package`, and has no effect without one.
+fn generate_from_program(
+    files: &FilesSourceText,
+    hlir_program: Program,
+    config: &MoveToTsOptions,
+    output_root: &Path,
+    root_package: Option<Symbol>,
+) -> Result<Generated, GenerateError> {
+    // contexts read the mappings without checking them again
+    config.external_packages().map_err(GenerateError::Package)?;
+    let hlir_program = match root_package {
+        Some(root_package) if config.prune => prune_program(&hlir_program, root_package),
+        _ => hlir_program,
+    };
+    let hlir_program = Arc::new(hlir_program);
+
+    // 2 & 3
+    let mut output = vec![];
+    let mut generated_modules = vec![];
+    let mut ctx = Context::new(config, hlir_program.clone());
+    // npm modules of --external-package packages, keyed by the package namespace they export
+    let mut external_namespaces = BTreeMap::new();
+    for (mident, mdef) in hlir_program.modules.key_cloned_iter() {
+        let package_name = mdef.package_name.map(|name| name.to_string());
+        if !is_module_included(config, &mident, package_name.as_deref()) {
+            ctx.excluded_modules.insert(mident);
+        }
+        if let Some(external) = ctx.external_package(&mident) {
+            external_namespaces.insert(format_address(mident.value.address), external.clone());
+        }
+    }
+    let midents = hlir_program
+        .modules
+        .key_cloned_iter()
+        .map(|(mident, _)| mident)
+        .filter(|mident| !ctx.excluded_modules.contains(mident))
+        .filter(|mident| ctx.external_package(mident).is_none())
+        .collect::<Vec<_>>();
+
+    // 2
+    let module_outputs =
+        parallel::translate_modules(midents, hlir_program, config, &ctx.excluded_modules);
+
+    for module_output in module_outputs.into_iter() {
+        let ModuleOutput {
+            mident,
+            ctx: module_ctx,
+            module,
+            tests,
+        } = module_output;
+
+        // 3
+        match recover_diagnostics(files, config, &mut ctx.diagnostics, module)? {
+            Some((filename, content, line_locs)) => {
+                ctx.merge(module_ctx);
+                let source_map = if config.source_map {
+                    let ts_path = Path::new("src").join(&filename);
+                    generate_source_map(&ts_path, &content, &line_locs, files, output_root)
+                } else {
+                    None
+                };
+                match source_map {
+                    Some(map) => {
+                        let map_filename = format!("{}.map", filename);
+                        let map_basename = Path::new(&map_filename).file_name().unwrap();
+                        let content = format!(
+                            "{}//# sourceMappingURL={}\n",
+                            content,
+                            map_basename.to_string_lossy()
+                        );
+                        output.push(GeneratedFile::new("src", (filename, content)));
+                        output.push(GeneratedFile::new("src", (map_filename, map)));
+                    }
+                    None => output.push(GeneratedFile::new("src", (filename, content))),
+                }
+                generated_modules.push(mident);
+            }
+            // --lenient: a module that failed to generate leaves nothing but its diagnostics
+            // behind, so that it is neither exported from index.ts nor called from the cli. Its
+            // tests were not generated either.
+            None => {
+                ctx.diagnostics.extend(module_ctx.diagnostics);
+                ctx.warnings.extend(module_ctx.warnings);
+                continue;
+            }
+        }
+
+        // 4 tests
+        if let Some(test_res) = tests {
+            if let Some(pair) = recover_diagnostics(files, config, &mut ctx.diagnostics, test_res)?
+            {
+                output.push(GeneratedFile::new("src/tests", pair));
+            }
+        }
+    }
+
+    // 5
+    if config.cli {
+        let cli_res = generate_cli(&mut ctx);
+        if let Some(pair) = recover_diagnostics(files, config, &mut ctx.diagnostics, cli_res)? {
+            output.push(GeneratedFile::new("src", pair));
+        }
+    }
+
+    if config.ui {
+        let ui_res = generate_ui(&mut ctx);
+        let ui_files = recover_diagnostics(files, config, &mut ctx.diagnostics, ui_res)?;
+        for pair in ui_files.unwrap_or_default().into_iter() {
+            output.push(GeneratedFile::new("src", pair));
+        }
+
+        output.push(GeneratedFile::new("public", gen_public_html()));
+    }
+
+    // 6
+    if !config.package_json_name.is_empty() {
+        // keys added by hand survive; so does the version, unless --package-version is given
+        let mut json_merger = JsonMerger::new(output_root);
+        let mut merge =
+            |(filename, content): (String, String), keep: &[&str], keep_edited: &[&str]| {
+                json_merger
+                    .merge(Path::new(&filename), &content, keep, keep_edited)
+                    .map(|content| GeneratedFile::new("", (filename, content)))
+                    .map_err(GenerateError::Package)
+            };
+
+        // package.json
+        let package_json = utils::generate_package_json(config, &external_namespaces)
+            .map_err(GenerateError::Package)?;
+        let keep: &[&str] = if config.package_version.is_empty() {
+            &["version"]
+        } else {
+            &[]
+        };
+        // dependency versions edited by hand survive too, unless move-to-ts changes them
+        output.push(merge(
+            package_json,
+            keep,
+            &["dependencies", "devDependencies"],
+        )?);
+
+        // tsconfig.json
+        for pair in utils::generate_ts_config(config.module(), config.numbers()) {
+            output.push(merge(pair, &[], &[])?);
+        }
+        output.push(GeneratedFile::new(
+            "",
+            json_merger.finish().map_err(GenerateError::Package)?,
+        ));
+
+        // jest.config.js
+        if !config.test_address.is_empty() {
+            output.push(GeneratedFile::new(
+                "",
+                utils::generate_jest_config(config.module(), &config.test_path_ignore_patterns),
+            ));
+        }
+    }
+
+    // 7
+    for (package_name, address) in ctx.visited_packages.iter() {
+        let modules = ctx
+            .visited_modules
+            .iter()
+            .filter(|mi| is_same_package(mi.value.address, *address))
+            .collect::<Vec<_>>();
+
+        let (filename, content) =
+            generate_index(package_name, &modules, config.module(), config.numbers());
+        output.push(GeneratedFile::new("src", (filename, content)));
+    }
+
+    output.push(GeneratedFile::new(
+        "src",
+        utils::generate_addresses(&ctx.program),
+    ));
+
+    // cannot generat topmost index.ts when generating a React playground
+    if !config.ui {
+        let package_names = ctx.visited_packages.keys().collect::<Vec<_>>();
+        output.push(GeneratedFile::new(
+            "src",
+            generate_topmost_index(
+                &package_names,
+                &external_namespaces,
+                config.module(),
+                config.numbers(),
+            ),
+        ));
+    }
+
+    output.push(GeneratedFile::new(
+        "",
+        manifest::generate_manifest(&generated_modules, &ctx),
+    ));
+
+    let mut warnings = ctx.warnings;
+    // --lenient: everything that got stubbed or skipped
+    warnings.extend(ctx.diagnostics);
+
+    Ok(Generated {
+        files: output,
+        warnings,
+        sources: files.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// Generates the package made of `sources` (a module per entry) with the given flags
+    fn generate_sources(name: &str, sources: &[&str], args: &[&str]) -> Vec<GeneratedFile> {
+        let package_path =
+            std::env::temp_dir().join(format!("move-to-ts-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&package_path);
+        std::fs::create_dir_all(package_path.join("sources")).unwrap();
+        std::fs::write(
+            package_path.join("Move.toml"),
+            "[package]\nname = \"Test\"\nversion = \"0.0.1\"\n\n[addresses]\ntest = \"0x42\"\n",
+        )
+        .unwrap();
+        for (idx, source) in sources.iter().enumerate() {
+            std::fs::write(
+                package_path.join("sources").join(format!("m{}.move", idx)),
+                source,
+            )
+            .unwrap();
+        }
+        let config =
+            MoveToTsOptions::parse_from(std::iter::once("move-to-ts").chain(args.iter().copied()));
+        let generated = generate(&package_path, &config);
+        let _ = std::fs::remove_dir_all(&package_path);
+        match generated {
+            Ok(generated) => generated.files,
+            Err(_) => panic!("Failed to generate {}", name),
+        }
+    }
+
+    fn module_content<'a>(files: &'a [GeneratedFile], module: &str) -> &'a str {
+        files
+            .iter()
+            .find(|file| file.path.ends_with(format!("{}.ts", module)))
+            .map(|file| file.content.as_str())
+            .unwrap()
+    }
+
+    #[test]
+    fn bigint_natives_box_arguments_and_write_back_mut_refs() {
+        let files = generate_sources(
+            "bigint-natives",
+            &[r#"
+module test::counter {
+    native fun bump(value: &mut u64, by: u8): u128;
+
+    public fun bump_twice(value: &mut u64): u128 {
+        bump(value, 1);
+        bump(value, 1)
+    }
+}
+"#],
+            &["--numbers", "bigint"],
+        );
+        let content = module_content(&files, "counter");
+        for expected in [
+            "const $boxed_value = $.boxedInPlace(value, AtomicTypeTag.U64);",
+            "$boxed_value, $.boxed(by, AtomicTypeTag.U8), $c)",
+            "$.writeBack(value, $boxed_value, AtomicTypeTag.U64);",
+            "return $.unboxed($result, AtomicTypeTag.U128);",
+        ] {
+            assert!(
+                content.contains(expected),
+                "{} not in:\n{}",
+                expected,
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn boxed_natives_are_called_directly() {
+        let files = generate_sources(
+            "boxed-natives",
+            &["module test::counter { native fun bump(value: &mut u64, by: u8): u128; }"],
+            &[],
+        );
+        let content = module_content(&files, "counter");
+        assert!(!content.contains("$.boxedInPlace"), "{}", content);
+        assert!(!content.contains("$.unboxed"), "{}", content);
+        assert!(content.contains("(value, by, $c)"), "{}", content);
+    }
+}
@@ -1,242 +1,170 @@
-mod ast_exp;
-mod ast_tests;
-pub mod ast_to_ts;
-pub mod gen_cli;
-pub mod gen_ui;
-mod shared;
-pub mod tsgen_writer;
-pub mod utils;
+mod watch;
 
-use crate::gen_cli::generate_cli;
-use crate::gen_ui::{gen_public_html, generate_ui};
-use crate::shared::{format_address, format_address_hex, is_same_package};
-use crate::utils::{generate_index, generate_topmost_index};
 use clap::Parser;
-use move_command_line_common::address::NumericalAddress;
-use move_command_line_common::parser::NumberFormat;
-use move_compiler::diagnostics::unwrap_or_report_diagnostics;
-use move_compiler::shared::PackagePaths;
-use move_compiler::*;
-use move_package::compilation::package_layout::CompiledPackageLayout;
+use move_package::resolution::resolution_graph::ResolvedGraph;
 use move_package::source_package::layout::SourcePackageLayout;
-use shared::{Context, MoveToTsOptions};
-use std::collections::BTreeMap;
+use move_to_ts::check::check_outputs;
+use move_to_ts::config::load_file_options;
+use move_to_ts::output::IncrementalWriter;
+use move_to_ts::report::{report_message, report_note};
+use move_to_ts::shared::MoveToTsOptions;
+use move_to_ts::{
+    bytecode_output_root, generate_from_bytecode, generate_from_graph, output_root, source_dirs,
+    GenerateError, Generated,
+};
 use std::path::Path;
 use std::process;
-use std::rc::Rc;
 
-fn write_file(root_path: &Path, pair: (String, String)) {
-    let (filename, content) = pair;
-    let path_to_save = root_path.join(filename);
-    let parent = path_to_save.parent().unwrap();
-    std::fs::create_dir_all(&parent).expect("Failed to create directory");
-    std::fs::write(path_to_save, content).expect("Failed to write file to output");
+fn build(resolution_graph: &ResolvedGraph, config: &MoveToTsOptions) -> bool {
+    write_outputs(
+        generate_from_graph(resolution_graph, config),
+        &output_root(resolution_graph, config),
+        config,
+    )
 }
 
-fn build(path: &Path, config: &MoveToTsOptions) {
-    let build_config = move_package::BuildConfig::default();
-    let resolution_graph = build_config
-        .resolution_graph_for_package(path)
-        .expect("Failed to build resolution graph for package");
-    /*
-    1. Go through the compilation pipeline to report diagnostics if any, otherwise retain AST
-       from the typing stage
-    2. feed typing AST through move-tsgen to get files
-    3. write files
-    4. write jest .test.ts files if --test is given
-    5. write cli and ui if --gen-cli and --gen-tui is given
-    6. write package.json and tsconfig.json if --generate-package is given
-    7. generate various index.ts for packages
-     */
-    let root_package = &resolution_graph.package_table[&resolution_graph.root_package.package.name];
-    let project_root = match &resolution_graph.build_options.install_dir {
-        Some(under_path) => under_path.clone(),
-        None => resolution_graph.root_package_path.clone(),
-    };
-
-    // 1
-    let mut named_address_mapping = BTreeMap::new();
-
-    resolution_graph
-        .extract_named_address_mapping()
-        .for_each(|(name, addr)| {
-            named_address_mapping.insert(
-                name,
-                NumericalAddress::new(addr.into_bytes(), NumberFormat::Hex),
-            );
-        });
-
-    let mut dependencies = resolution_graph
-        .package_table
-        .iter()
-        .filter_map(|(name, package)| {
-            if name == &root_package.source_package.package.name {
-                None
-            } else {
-                let pkg_name = package.package_path.to_string_lossy();
-                let mut paths = vec![format!("{}/sources", pkg_name)];
-                if !config.test_address.is_empty() && package.package_path.join("tests").is_dir() {
-                    paths.push(format!("{}/tests", pkg_name))
-                }
-                let path = PackagePaths {
-                    name: Some(*name),
-                    paths,
-                    named_address_map: named_address_mapping.clone(),
-                };
-                Some(path)
+fn write_outputs(
+    generated: Result<Generated, GenerateError>,
+    root_path: &Path,
+    config: &MoveToTsOptions,
+) -> bool {
+    match generated {
+        Ok(mut generated) => {
+            generated.report_warnings(config);
+            let mut output = IncrementalWriter::new(root_path);
+            for file in generated.files.iter() {
+                output.write_file(file);
             }
-        })
-        .collect::<Vec<_>>();
-
-    let sources = vec![format!(
-        "{}/sources",
-        root_package.package_path.to_string_lossy()
-    )];
-
-    let mut source_package_paths = vec![PackagePaths {
-        name: Some(root_package.source_package.package.name),
-        paths: sources,
-        named_address_map: named_address_mapping,
-    }];
-
-    source_package_paths.append(&mut dependencies);
-
-    let flags = if !config.test_address.is_empty() {
-        Flags::testing()
-    } else {
-        Flags::empty()
-    };
-
-    // mark everything as source to avoid all functions in dependencies being marked as "native"
-    let compiler =
-        Compiler::from_package_paths(source_package_paths.clone(), vec![]).set_flags(flags);
-
-    let (files, res_comments_compiler) = compiler
-        .run::<{ move_compiler::PASS_HLIR }>()
-        .expect("Compilation failed");
-
-    let (_comments, hlir_compiler) = unwrap_or_report_diagnostics(&files, res_comments_compiler);
-
-    let (_, hlir_program) = hlir_compiler.into_ast();
-    let hlir_program = Rc::new(hlir_program);
-
-    // run the full pipeline to check errors/warnings
-    // move package doesn't provide a way to save intermediate program ast, so rerunning the
-    // entire pipeline to check all errors. We need to make a PR upstream to avoid doing repeat
-    // work here.
-
-    // commented out for faster testing >.<
-    /*
-    let compiler = Compiler::from_package_paths(source_package_paths, vec![]);
-    let (_, full_res) = compiler
-        .run::<{ move_compiler::PASS_COMPILATION }>()
-        .expect("Compilation failed");
-    unwrap_or_report_diagnostics(&files, full_res);
-     */
-
-    // 2 & 3
-    let build_root_path = if config.output_path.clone().into_os_string().is_empty() {
-        project_root
-            .join(CompiledPackageLayout::Root.path())
-            .join("typescript")
-    } else {
-        config.output_path.clone()
-    };
-    let mut ctx = Context::new(config, hlir_program.clone());
-    for (mident, mdef) in hlir_program.modules.key_cloned_iter() {
-        // skip problematic modules under aptos_framework::aggregator*
-        let mod_name = mident.value.module.to_string();
-        if format_address_hex(mident.value.address) == "0x1" && mod_name.contains("secp256k1") {
-            continue;
+            for removed in output.finish() {
+                report_note(
+                    &format!("Removed stale file {}", removed.to_string_lossy()),
+                    None,
+                    config,
+                );
+            }
+            true
         }
-
-        // 2
-        let result = ast_to_ts::translate_module(mident, mdef, &mut ctx);
-
-        let (filename, content) = unwrap_or_report_diagnostics(&files, result);
-
-        // 3
-        write_file(&build_root_path.join("src"), (filename, content));
-
-        // 4 tests
-        let test_matches = config.test_address == "all"
-            || format_address(mident.value.address) == config.test_address;
-        if test_matches && !ctx.tests.is_empty() {
-            let test_res = ast_tests::generate_tests(&mut ctx);
-            let (filename, content) = unwrap_or_report_diagnostics(&files, test_res);
-            write_file(&build_root_path.join("src/tests"), (filename, content));
+        Err(err) => {
+            // reported without exiting so that watch mode survives a bad edit
+            err.report(config);
+            false
         }
     }
+}
 
-    // 5
-    if config.cli {
-        let (filename, content) = unwrap_or_report_diagnostics(&files, generate_cli(&ctx));
-        write_file(&build_root_path.join("src"), (filename, content));
-    }
-
-    if config.ui {
-        let files = unwrap_or_report_diagnostics(&files, generate_ui(&mut ctx));
-        for (filename, content) in files.iter() {
-            write_file(
-                &build_root_path.join("src"),
-                (filename.clone(), content.clone()),
-            );
+fn check(generated: Result<Generated, GenerateError>, root_path: &Path, config: &MoveToTsOptions) {
+    let up_to_date = match generated {
+        Ok(mut generated) => {
+            generated.report_warnings(config);
+            let outcome = check_outputs(root_path, &generated.files);
+            for (path, diff) in outcome.diffs.iter() {
+                report_note(
+                    &format!("Out of date: {}", path),
+                    Some(diff.as_str()),
+                    config,
+                );
+            }
+            for path in outcome.stale.iter() {
+                report_note(
+                    &format!("Stale generated file would be removed: {}", path),
+                    None,
+                    config,
+                );
+            }
+            outcome.is_up_to_date()
         }
-
-        let (filename, content) = gen_public_html();
-        write_file(&build_root_path.join("public"), (filename, content));
-    }
-
-    // 6
-    if !config.package_json_name.is_empty() {
-        // package.json
-        let (filename, content) =
-            utils::generate_package_json(config.package_json_name.clone(), config.cli, config.ui);
-        write_file(&build_root_path, (filename, content));
-
-        // tsconfig.json
-        let (filename, content) = utils::generate_ts_config();
-        write_file(&build_root_path, (filename, content));
-
-        // jest.config.js
-        if !config.test_address.is_empty() {
-            let (filename, content) = utils::generate_jest_config();
-            write_file(&build_root_path, (filename, content));
+        Err(err) => {
+            err.report(config);
+            false
         }
+    };
+    if !up_to_date {
+        report_message(
+            "Generated TypeScript is out of date, please rerun move-to-ts",
+            config,
+        );
+        process::exit(1);
     }
+    report_note("Generated TypeScript is up to date", None, config);
+}
 
-    // 7
-    for (package_name, address) in ctx.visited_packages.iter() {
-        let modules = ctx
-            .visited_modules
-            .iter()
-            .filter(|mi| is_same_package(mi.value.address, *address))
-            .collect::<Vec<_>>();
-
-        let (filename, content) = generate_index(package_name, &modules);
-        write_file(&build_root_path.join("src"), (filename, content));
-    }
-
-    // cannot generat topmost index.ts when generating a React playground
-    if !config.ui {
-        let package_names = ctx.visited_packages.keys().collect::<Vec<_>>();
-        write_file(
-            &build_root_path.join("src"),
-            generate_topmost_index(&package_names),
-        )
+/// --bytecode: no package to resolve, outputs go to --output-path or build/typescript
+fn main_bytecode(args: &MoveToTsOptions) {
+    let root_path = bytecode_output_root(args);
+    let generated = generate_from_bytecode(&args.bytecode, args);
+    if args.check {
+        check(generated, &root_path, args);
+    } else if !write_outputs(generated, &root_path, args) {
+        process::exit(1);
     }
 }
 
 fn main() {
-    let args = MoveToTsOptions::parse();
+    let mut args = MoveToTsOptions::parse();
+
+    if !args.bytecode.is_empty() {
+        main_bytecode(&args);
+        return;
+    }
 
     let root = SourcePackageLayout::try_find_root(&args.package_path);
     if root.is_err() {
-        println!("Please provide path to valid move package or run this command from within one");
+        report_message(
+            "Please provide path to valid move package or run this command from within one",
+            &args,
+        );
         process::exit(-1);
     }
     let root_path = root.unwrap();
     std::env::set_current_dir(&root_path).unwrap();
-    println!("Working from {}", root_path.to_string_lossy());
-    build(&root_path, &args);
+
+    let merged = load_file_options(&root_path).and_then(|file_options| match file_options {
+        Some(file_options) => args.merge_file_options(file_options),
+        None => Ok(()),
+    });
+    if let Err(err) = merged {
+        report_message(
+            &format!("Failed to load move-to-ts options: {:#}", err),
+            &args,
+        );
+        process::exit(-1);
+    }
+    // after merging, so that --message-format from the config file applies
+    report_note(
+        &format!("Working from {}", root_path.to_string_lossy()),
+        None,
+        &args,
+    );
+
+    let resolution_graph = match args
+        .build_config()
+        .and_then(|build_config| build_config.resolution_graph_for_package(&root_path))
+    {
+        Ok(resolution_graph) => resolution_graph,
+        Err(err) => {
+            report_message(&format!("{:#}", err), &args);
+            process::exit(-1);
+        }
+    };
+
+    if args.check {
+        check(
+            generate_from_graph(&resolution_graph, &args),
+            &output_root(&resolution_graph, &args),
+            &args,
+        );
+        return;
+    }
+
+    let succeeded = build(&resolution_graph, &args);
+    if args.watch {
+        let dirs = source_dirs(&resolution_graph, &args);
+        watch::watch(&dirs, &args, || {
+            if build(&resolution_graph, &args) {
+                report_note("Regenerated TypeScript", None, &args);
+            }
+        });
+    } else if !succeeded {
+        process::exit(1);
+    }
 }
@@ -0,0 +1,316 @@
+use crate::ast_to_ts::script_function_has_valid_parameter;
+use crate::shared::{format_address, format_address_hex, Context};
+use crate::utils::{capitalize, rename};
+use itertools::Itertools;
+use move_compiler::expansion::ast::{AbilitySet, ModuleIdent, Visibility};
+use move_compiler::hlir::ast::*;
+use move_compiler::parser::ast::Ability_;
+use serde::Serialize;
+
+pub const MANIFEST_FILENAME: &str = "move-to-ts.manifest.json";
+
+/// Maps every generated TypeScript symbol back to the Move item it came from. `file` is relative
+/// to the output root, `export` is the name under which the symbol is reachable from the
+/// topmost index (e.g. stdlib.Coin.transfer_).
+#[derive(Serialize)]
+pub struct Manifest {
+    pub modules: Vec<ModuleEntry>,
+    pub directives: Vec<DirectiveEntry>,
+}
+
+#[derive(Serialize)]
+pub struct ModuleEntry {
+    /// e.g. 0x1::coin
+    pub move_name: String,
+    pub package: Option<String>,
+    pub file: String,
+    pub export: String,
+    pub structs: Vec<StructEntry>,
+    pub functions: Vec<FunctionEntry>,
+}
+
+#[derive(Serialize)]
+pub struct StructEntry {
+    pub move_name: String,
+    pub export: String,
+    pub abilities: Vec<&'static str>,
+    pub type_parameters: Vec<TypeParameterEntry>,
+    /// None for native structs
+    pub fields: Option<Vec<NamedType>>,
+}
+
+#[derive(Serialize)]
+pub struct TypeParameterEntry {
+    pub name: String,
+    pub is_phantom: bool,
+}
+
+#[derive(Serialize)]
+pub struct NamedType {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+#[derive(Serialize)]
+pub struct FunctionEntry {
+    pub move_name: String,
+    pub export: String,
+    pub visibility: &'static str,
+    pub entry: bool,
+    pub type_parameters: Vec<String>,
+    pub parameters: Vec<NamedType>,
+    pub return_type: String,
+    /// buildPayload_* builder, for entry functions that can be called in a transaction
+    pub payload_builder: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DirectiveEntry {
+    /// cmd, cmd_printer, query, method or show_iter_table
+    pub directive: &'static str,
+    /// the annotated Move item
+    pub move_name: String,
+    pub file: String,
+    pub export: String,
+}
+
+fn format_abilities(abilities: &AbilitySet) -> Vec<&'static str> {
+    [
+        (Ability_::Copy, "copy"),
+        (Ability_::Drop, "drop"),
+        (Ability_::Store, "store"),
+        (Ability_::Key, "key"),
+    ]
+    .into_iter()
+    .filter(|(ability, _)| abilities.has_ability_(*ability))
+    .map(|(_, name)| name)
+    .collect()
+}
+
+/// Move syntax for a type, with addresses in hex
+pub fn format_base_type(ty: &BaseType) -> String {
+    match &ty.value {
+        BaseType_::Param(tparam) => tparam.user_specified_name.to_string(),
+        BaseType_::Apply(_, typename, targs) => {
+            let name = match &typename.value {
+                TypeName_::Builtin(builtin) => builtin.value.to_string(),
+                TypeName_::ModuleType(mi, sname) => format!(
+                    "{}::{}::{}",
+                    format_address_hex(mi.value.address),
+                    mi.value.module,
+                    sname
+                ),
+            };
+            if targs.is_empty() {
+                name
+            } else {
+                format!(
+                    "{}<{}>",
+                    name,
+                    targs.iter().map(format_base_type).join(", ")
+                )
+            }
+        }
+        _ => "_".to_string(),
+    }
+}
+
+pub fn format_single_type(ty: &SingleType) -> String {
+    match &ty.value {
+        SingleType_::Base(base) => format_base_type(base),
+        SingleType_::Ref(true, base) => format!("&mut {}", format_base_type(base)),
+        SingleType_::Ref(false, base) => format!("&{}", format_base_type(base)),
+    }
+}
+
+pub fn format_type(ty: &Type) -> String {
+    match &ty.value {
+        Type_::Unit => "()".to_string(),
+        Type_::Single(single) => format_single_type(single),
+        Type_::Multiple(types) => format!("({})", types.iter().map(format_single_type).join(", ")),
+    }
+}
+
+fn move_module_name(mi: &ModuleIdent) -> String {
+    format!(
+        "{}::{}",
+        format_address_hex(mi.value.address),
+        mi.value.module
+    )
+}
+
+fn module_file(mi: &ModuleIdent) -> String {
+    format!(
+        "src/{}/{}.ts",
+        format_address(mi.value.address),
+        mi.value.module
+    )
+}
+
+fn module_export(mi: &ModuleIdent) -> String {
+    format!(
+        "{}.{}",
+        format_address(mi.value.address),
+        capitalize(&mi.value.module)
+    )
+}
+
+fn struct_entry(
+    mi: &ModuleIdent,
+    sname: &impl std::fmt::Display,
+    sdef: &StructDefinition,
+) -> StructEntry {
+    StructEntry {
+        move_name: format!("{}::{}", move_module_name(mi), sname),
+        export: format!("{}.{}", module_export(mi), sname),
+        abilities: format_abilities(&sdef.abilities),
+        type_parameters: sdef
+            .type_parameters
+            .iter()
+            .map(|tp| TypeParameterEntry {
+                name: tp.param.user_specified_name.to_string(),
+                is_phantom: tp.is_phantom,
+            })
+            .collect(),
+        fields: match &sdef.fields {
+            StructFields::Native(_) => None,
+            StructFields::Defined(fields) => Some(
+                fields
+                    .iter()
+                    .map(|(name, ty)| NamedType {
+                        name: rename(name),
+                        ty: format_base_type(ty),
+                    })
+                    .collect(),
+            ),
+        },
+    }
+}
+
+fn function_entry(
+    mi: &ModuleIdent,
+    fname: &impl std::fmt::Display,
+    func: &Function,
+) -> FunctionEntry {
+    let is_entry = func.entry.is_some();
+    FunctionEntry {
+        move_name: format!("{}::{}", move_module_name(mi), fname),
+        export: format!("{}.{}_", module_export(mi), rename(fname)),
+        visibility: match &func.visibility {
+            Visibility::Public(_) => "public",
+            Visibility::Friend(_) => "friend",
+            Visibility::Internal => "private",
+        },
+        entry: is_entry,
+        type_parameters: func
+            .signature
+            .type_parameters
+            .iter()
+            .map(|tp| tp.user_specified_name.to_string())
+            .collect(),
+        parameters: func
+            .signature
+            .parameters
+            .iter()
+            .map(|(name, ty)| NamedType {
+                name: rename(name),
+                ty: format_single_type(ty),
+            })
+            .collect(),
+        return_type: format_type(&func.signature.return_type),
+        payload_builder: if is_entry && script_function_has_valid_parameter(&func.signature) {
+            Some(format!("{}.buildPayload_{}", module_export(mi), fname))
+        } else {
+            None
+        },
+    }
+}
+
+/// Builds move-to-ts.manifest.json for the modules that were generated. Directives are taken from
+/// what the context collected while translating them.
+pub fn generate_manifest(modules: &[ModuleIdent], c: &Context) -> (String, String) {
+    let modules = modules
+        .iter()
+        .map(|mi| {
+            let mdef = c.program.modules.get(mi).unwrap();
+            ModuleEntry {
+                move_name: move_module_name(mi),
+                package: mdef.package_name.map(|name| name.to_string()),
+                file: module_file(mi),
+                export: module_export(mi),
+                structs: mdef
+                    .structs
+                    .key_cloned_iter()
+                    .map(|(sname, sdef)| struct_entry(mi, &sname, sdef))
+                    .collect(),
+                functions: mdef
+                    .functions
+                    .key_cloned_iter()
+                    .map(|(fname, func)| function_entry(mi, &fname, func))
+                    .collect(),
+            }
+        })
+        .collect();
+
+    let mut directives = vec![];
+    for cmd in c.cmds.iter() {
+        directives.push(DirectiveEntry {
+            directive: "cmd",
+            move_name: format!("{}::{}", move_module_name(&cmd.mi), cmd.fname),
+            file: "src/cli.ts".to_string(),
+            export: format!(
+                "{}:{}",
+                cmd.mi.value.module.to_string().replace('_', "-"),
+                cmd.fname.to_string().replace('_', "-")
+            ),
+        });
+    }
+    for (mi, sname, _, fname, _) in c.printer_methods.iter() {
+        // the cli command printing sname
+        directives.push(DirectiveEntry {
+            directive: "cmd_printer",
+            move_name: format!("{}::{}", move_module_name(mi), fname),
+            file: "src/cli.ts".to_string(),
+            export: format!("{}:{}", sname, fname.to_string().replace('_', "-")),
+        });
+    }
+    for query in c.queries.iter() {
+        directives.push(DirectiveEntry {
+            directive: "query",
+            move_name: format!("{}::{}", move_module_name(&query.mi), query.fname),
+            file: module_file(&query.mi),
+            export: format!("{}.query_{}", module_export(&query.mi), query.fname),
+        });
+    }
+    for (mi, sname, fname) in c.methods.iter() {
+        directives.push(DirectiveEntry {
+            directive: "method",
+            move_name: format!("{}::{}", move_module_name(mi), fname),
+            file: module_file(mi),
+            export: format!("{}.{}.prototype.{}", module_export(mi), sname, fname),
+        });
+    }
+    for (mi, sname, _, field_name) in c.all_shows_iter_tables.iter() {
+        directives.push(DirectiveEntry {
+            directive: "show_iter_table",
+            move_name: format!("{}::{}.{}", move_module_name(mi), sname, field_name),
+            file: module_file(mi),
+            export: format!(
+                "{}.{}.prototype.getIterTableEntries_{}",
+                module_export(mi),
+                sname,
+                field_name
+            ),
+        });
+    }
+
+    let manifest = Manifest {
+        modules,
+        directives,
+    };
+    (
+        MANIFEST_FILENAME.to_string(),
+        serde_json::to_string_pretty(&manifest).unwrap() + "\n",
+    )
+}
@@ -0,0 +1,292 @@
+use crate::GeneratedFile;
+use anyhow::Context as _;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Lists every file written by the previous run, relative to the output root
+pub const OUTPUT_MANIFEST_FILENAME: &str = ".move-to-ts-files.json";
+
+/// Records the JSON generated for every merged file, before merging, relative to the output root
+pub const GENERATED_JSON_FILENAME: &str = ".move-to-ts-json.json";
+
+/// Only generated sources are pruned when they disappear from the output. package.json and friends
+/// are left alone so that dropping -n does not delete a user's files.
+const PRUNABLE_DIR: &str = "src";
+
+/// Files written by the previous run, relative to the output root
+pub fn read_output_manifest(root_path: &Path) -> BTreeSet<String> {
+    std::fs::read_to_string(root_path.join(OUTPUT_MANIFEST_FILENAME))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Previously generated files that the current run no longer produces and that may be deleted
+pub fn stale_files<'a>(
+    previous: &'a BTreeSet<String>,
+    is_current: impl Fn(&str) -> bool + 'a,
+) -> impl Iterator<Item = &'a String> + 'a {
+    previous.iter().filter(move |relative_path| {
+        !is_current(relative_path) && Path::new(relative_path).starts_with(PRUNABLE_DIR)
+    })
+}
+
+/// Merges `generated` into `existing`: objects key by key, keeping the keys only `existing` has. Any
+/// other value is replaced by the generated one.
+fn merge_json(existing: &mut Value, generated: Value) {
+    match (existing, generated) {
+        (Value::Object(existing), Value::Object(generated)) => {
+            for (key, value) in generated {
+                match existing.get_mut(&key) {
+                    Some(existing_value) => merge_json(existing_value, value),
+                    None => {
+                        existing.insert(key, value);
+                    }
+                }
+            }
+        }
+        (existing, generated) => *existing = generated,
+    }
+}
+
+/// Three-way merge of a map of leaves such as `dependencies`: an entry generated the same way as on
+/// the previous run keeps its existing value, which may have been edited by hand. Entries that the
+/// generator changed (e.g. through --dependency) take the generated value.
+fn keep_edited_values(existing: Option<&Value>, previous: Option<&Value>, generated: &mut Value) {
+    if let (Some(Value::Object(existing)), Value::Object(generated)) = (existing, generated) {
+        for (name, value) in generated.iter_mut() {
+            let unchanged = match previous.and_then(|previous| previous.get(name)) {
+                Some(previous_value) => previous_value == value,
+                None => true,
+            };
+            if let (true, Some(existing_value)) = (unchanged, existing.get(name)) {
+                *value = existing_value.clone();
+            }
+        }
+    }
+}
+
+/// Removes from `existing` the keys that the previous run generated and this one does not, at any
+/// depth. Keys that were never generated were added by hand and are left alone.
+fn remove_dropped_keys(existing: &mut Value, previous: &Value, generated: &Value) {
+    if let (Value::Object(existing), Value::Object(previous), Value::Object(generated)) =
+        (existing, previous, generated)
+    {
+        let dropped = previous
+            .keys()
+            .filter(|key| !generated.contains_key(*key))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        // rebuilt rather than removed from, which would reorder the keys
+        *existing = std::mem::take(existing)
+            .into_iter()
+            .filter(|(key, _)| !dropped.contains(key))
+            .collect();
+        for (key, previous_value) in previous.iter() {
+            if let (Some(existing_value), Some(generated_value)) =
+                (existing.get_mut(key), generated.get(key))
+            {
+                remove_dropped_keys(existing_value, previous_value, generated_value);
+            }
+        }
+    }
+}
+
+/// Merges generated JSON files (package.json, tsconfig.json) into the ones already under the
+/// output root, so that keys added by hand survive regeneration. What the generator produced is
+/// recorded in GENERATED_JSON_FILENAME, so that keys it no longer produces (e.g. `exports` after
+/// switching back to CommonJS) are removed rather than mistaken for hand-written ones.
+pub struct JsonMerger<'a> {
+    root_path: &'a Path,
+    previous: Map<String, Value>,
+    generated: Map<String, Value>,
+}
+
+impl<'a> JsonMerger<'a> {
+    pub fn new(root_path: &'a Path) -> Self {
+        let previous = std::fs::read_to_string(root_path.join(GENERATED_JSON_FILENAME))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            root_path,
+            previous,
+            generated: Map::new(),
+        }
+    }
+
+    /// The merged content of `relative_path`. The top-level `keep` keys retain their existing
+    /// values; entries of the top-level `keep_edited` maps retain theirs unless the generator
+    /// changed them since the previous run.
+    pub fn merge(
+        &mut self,
+        relative_path: &Path,
+        generated: &str,
+        keep: &[&str],
+        keep_edited: &[&str],
+    ) -> anyhow::Result<String> {
+        let mut generated: Value = serde_json::from_str(generated)?;
+        let path = self.root_path.join(relative_path);
+        let mut merged = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).with_context(|| {
+                format!(
+                    "Cannot merge into {}, please fix or remove it",
+                    path.to_string_lossy()
+                )
+            })?,
+            Err(_) => Value::Object(Map::new()),
+        };
+        let key = relative_path.to_string_lossy().to_string();
+        let previous = self.previous.get(&key);
+        if let Some(previous) = previous {
+            remove_dropped_keys(&mut merged, previous, &generated);
+        }
+        let record = generated.clone();
+        if let (Value::Object(existing), Value::Object(generated)) = (&merged, &mut generated) {
+            for key in keep {
+                if let Some(value) = existing.get(*key) {
+                    generated.insert(key.to_string(), value.clone());
+                }
+            }
+            for key in keep_edited {
+                if let Some(generated_value) = generated.get_mut(*key) {
+                    let previous_value = previous.and_then(|previous| previous.get(*key));
+                    keep_edited_values(existing.get(*key), previous_value, generated_value);
+                }
+            }
+        }
+        self.generated.insert(key, record);
+        merge_json(&mut merged, generated);
+        Ok(serde_json::to_string_pretty(&merged)? + "\n")
+    }
+
+    /// The record of what was generated, to be written with the merged files
+    pub fn finish(self) -> anyhow::Result<(String, String)> {
+        Ok((
+            GENERATED_JSON_FILENAME.to_string(),
+            serde_json::to_string_pretty(&self.generated)? + "\n",
+        ))
+    }
+}
+
+/// Writes generated files under a build root, skipping files whose content on disk is already up to
+/// date so that mtimes stay stable for tsc --incremental and bundlers.
+pub struct IncrementalWriter {
+    root_path: PathBuf,
+    previous: BTreeSet<String>,
+    current: BTreeSet<String>,
+}
+
+impl IncrementalWriter {
+    pub fn new(root_path: &Path) -> Self {
+        Self {
+            root_path: root_path.to_path_buf(),
+            previous: read_output_manifest(root_path),
+            current: BTreeSet::new(),
+        }
+    }
+
+    /// Writes `file` under the build root unless it is unchanged on disk. Comparing with the file
+    /// itself means that hand-edited or truncated outputs are restored.
+    pub fn write_file(&mut self, file: &GeneratedFile) {
+        let relative_path = file.path.to_string_lossy().to_string();
+        let path_to_save = self.root_path.join(&relative_path);
+        let unchanged = std::fs::read(&path_to_save)
+            .map(|existing| existing == file.content.as_bytes())
+            .unwrap_or(false);
+        self.current.insert(relative_path);
+        if unchanged {
+            return;
+        }
+        let parent = path_to_save.parent().unwrap();
+        std::fs::create_dir_all(&parent).expect("Failed to create directory");
+        std::fs::write(path_to_save, &file.content).expect("Failed to write file to output");
+    }
+
+    /// Deletes generated sources that were not produced by this run and saves the new manifest.
+    /// Only call this after a successful run, otherwise outputs of failed modules get removed.
+    /// Returns the deleted files.
+    pub fn finish(self) -> Vec<PathBuf> {
+        let current = &self.current;
+        let mut removed = vec![];
+        for relative_path in stale_files(&self.previous, |path| current.contains(path)) {
+            let stale_path = self.root_path.join(relative_path);
+            if std::fs::remove_file(&stale_path).is_ok() {
+                // clean up directories of packages that no longer exist; fails if non-empty
+                if let Some(parent) = stale_path.parent() {
+                    let _ = std::fs::remove_dir(parent);
+                }
+                removed.push(stale_path);
+            }
+        }
+        let manifest =
+            serde_json::to_string_pretty(&self.current).expect("Failed to serialize manifest");
+        std::fs::create_dir_all(&self.root_path).expect("Failed to create directory");
+        std::fs::write(self.root_path.join(OUTPUT_MANIFEST_FILENAME), manifest)
+            .expect("Failed to write file to output");
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn keys_no_longer_generated_are_removed() {
+        let previous =
+            json!({"name": "sdk", "type": "module", "exports": {"import": "a", "require": "b"}});
+        let generated = json!({"name": "sdk", "exports": {"import": "a"}});
+        let mut existing = json!({
+            "name": "sdk",
+            "type": "module",
+            "exports": {"import": "a", "require": "b", "custom": "c"},
+            "scripts": {"lint": "eslint"},
+        });
+        remove_dropped_keys(&mut existing, &previous, &generated);
+        merge_json(&mut existing, generated);
+        assert_eq!(
+            existing,
+            json!({
+                "name": "sdk",
+                "exports": {"import": "a", "custom": "c"},
+                "scripts": {"lint": "eslint"},
+            })
+        );
+    }
+
+    #[test]
+    fn dependencies_edited_by_hand_are_kept() {
+        let previous = json!({"aptos": "1.3.10", "jest": "^27.5.1", "yaml": "^2.1.1"});
+        let existing = json!({"aptos": "1.3.16", "jest": "^27.5.1", "yaml": "^2.2.0", "zod": "3"});
+        // jest is bumped by the generator, aptos and yaml were edited by hand, lodash is new
+        let mut generated =
+            json!({"aptos": "1.3.10", "jest": "^29.0.0", "yaml": "^2.1.1", "lodash": "4"});
+        keep_edited_values(Some(&existing), Some(&previous), &mut generated);
+        assert_eq!(
+            generated,
+            json!({"aptos": "1.3.16", "jest": "^29.0.0", "yaml": "^2.2.0", "lodash": "4"})
+        );
+        // without a record of the previous run, existing values are assumed to be hand-edited
+        let mut generated = json!({"aptos": "1.3.10", "lodash": "4"});
+        keep_edited_values(Some(&existing), None, &mut generated);
+        assert_eq!(generated, json!({"aptos": "1.3.16", "lodash": "4"}));
+    }
+
+    #[test]
+    fn removal_keeps_key_order() {
+        let previous = json!({"a": 1, "b": 2, "c": 3});
+        let generated = json!({"a": 1, "c": 3});
+        let mut existing = json!({"a": 1, "b": 2, "x": 0, "c": 3});
+        remove_dropped_keys(&mut existing, &previous, &generated);
+        let keys = existing
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["a", "x", "c"]);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::ast_tests::generate_tests;
+use crate::ast_to_ts::translate_module;
+use crate::shared::{format_address, Context, MoveToTsOptions};
+use move_compiler::diagnostics::Diagnostics;
+use move_compiler::expansion::ast::ModuleIdent;
+use move_compiler::hlir::ast::Program;
+use move_ir_types::location::Loc;
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+// translation recurses over expressions, give workers the same stack as the main thread
+const WORKER_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Everything produced by translating one module. The context only holds what was collected for
+/// this module, and is merged into the global one afterwards.
+pub struct ModuleOutput {
+    pub mident: ModuleIdent,
+    pub ctx: Context,
+    /// filename, content, and the Move location of each line of the content
+    pub module: Result<(String, String, Vec<Option<Loc>>), Diagnostics>,
+    /// None if tests are not generated for this module, or the module itself failed
+    pub tests: Option<Result<(String, String), Diagnostics>>,
+}
+
+fn translate_one(
+    mident: ModuleIdent,
+    program: &Arc<Program>,
+    config: &MoveToTsOptions,
+    excluded_modules: &BTreeSet<ModuleIdent>,
+) -> ModuleOutput {
+    let mut ctx = Context::new(config, program.clone());
+    ctx.excluded_modules = excluded_modules.clone();
+    let mdef = program.modules.get(&mident).unwrap();
+    let module = translate_module(mident, mdef, &mut ctx);
+
+    let test_matches =
+        config.test_address == "all" || format_address(mident.value.address) == config.test_address;
+    // tests would only run into the stubbed bodies of --surface-only
+    let tests = if module.is_ok() && test_matches && !ctx.tests.is_empty() && !config.surface_only {
+        Some(generate_tests(&mut ctx))
+    } else {
+        None
+    };
+
+    ModuleOutput {
+        mident,
+        ctx,
+        module,
+        tests,
+    }
+}
+
+/// Translates modules on all available cores, each with a context of its own. Outputs are
+/// returned in the order of `midents`.
+pub fn translate_modules(
+    midents: Vec<ModuleIdent>,
+    program: Arc<Program>,
+    config: &MoveToTsOptions,
+    excluded_modules: &BTreeSet<ModuleIdent>,
+) -> Vec<ModuleOutput> {
+    let num_workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(midents.len())
+        .max(1);
+    let midents = Arc::new(midents);
+    let next = Arc::new(AtomicUsize::new(0));
+    let config = Arc::new(config.clone());
+    let excluded_modules = Arc::new(excluded_modules.clone());
+
+    let workers = (0..num_workers)
+        .map(|_| {
+            let midents = midents.clone();
+            let next = next.clone();
+            let program = program.clone();
+            let config = config.clone();
+            let excluded_modules = excluded_modules.clone();
+            thread::Builder::new()
+                .stack_size(WORKER_STACK_SIZE)
+                .spawn(move || {
+                    let mut done = vec![];
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        if idx >= midents.len() {
+                            break;
+                        }
+                        let output =
+                            translate_one(midents[idx], &program, &config, &excluded_modules);
+                        done.push((idx, output));
+                    }
+                    done
+                })
+                .expect("Failed to spawn translation thread")
+        })
+        .collect::<Vec<_>>();
+
+    let mut outputs = workers
+        .into_iter()
+        .flat_map(|worker| {
+            worker
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+        .collect::<Vec<_>>();
+    outputs.sort_by_key(|(idx, _)| *idx);
+    outputs.into_iter().map(|(_, output)| output).collect()
+}
@@ -0,0 +1,297 @@
+use crate::shared::format_address_hex;
+use move_compiler::expansion::ast::{Attribute_, ModuleIdent};
+use move_compiler::hlir::ast::*;
+use move_compiler::parser::ast::FunctionName;
+use move_compiler::shared::unique_map::UniqueMap;
+use move_symbol_pool::Symbol;
+use std::collections::BTreeSet;
+
+/// Modules and functions reachable from the root package. A module is reachable as soon as one of
+/// its functions or structs is; all structs of a reachable module are kept, so their field types
+/// are followed too.
+struct Reachability<'a> {
+    program: &'a Program,
+    modules: BTreeSet<ModuleIdent>,
+    functions: BTreeSet<(ModuleIdent, FunctionName)>,
+    // functions found but not visited yet
+    pending: Vec<(ModuleIdent, FunctionName)>,
+}
+
+impl<'a> Reachability<'a> {
+    fn new(program: &'a Program) -> Self {
+        Self {
+            program,
+            modules: BTreeSet::new(),
+            functions: BTreeSet::new(),
+            pending: vec![],
+        }
+    }
+
+    fn add_module(&mut self, mident: ModuleIdent) {
+        if !self.modules.insert(mident) {
+            return;
+        }
+        let program = self.program;
+        let mdef = match program.modules.get(&mident) {
+            Some(mdef) => mdef,
+            None => return,
+        };
+        for (_, sdef) in mdef.structs.key_cloned_iter() {
+            if let StructFields::Defined(fields) = &sdef.fields {
+                for (_, ty) in fields.iter() {
+                    self.visit_base_type(ty);
+                }
+            }
+            // #[method(f)] turns f into a method of the struct, so it is kept with the struct
+            for (name, attr) in sdef.attributes.key_cloned_iter() {
+                if name.to_string() != "method" {
+                    continue;
+                }
+                if let Attribute_::Parameterized(_, inner_attrs) = &attr.value {
+                    for (_, inner_attr) in inner_attrs.key_cloned_iter() {
+                        if let Attribute_::Name(fname) = &inner_attr.value {
+                            self.add_function(mident, FunctionName(*fname));
+                        }
+                    }
+                }
+            }
+        }
+        // the hand-written TypedIterableTable helper calls into the module's own imports
+        if format_address_hex(mident.value.address) == "0x1"
+            && mident.value.module.to_string() == "iterable_table"
+        {
+            for (fname, _) in mdef.functions.key_cloned_iter() {
+                self.add_function(mident, fname);
+            }
+        }
+    }
+
+    fn add_function(&mut self, mident: ModuleIdent, fname: FunctionName) {
+        self.add_module(mident);
+        if self.functions.insert((mident, fname)) {
+            self.pending.push((mident, fname));
+        }
+    }
+
+    fn run(&mut self) {
+        while let Some((mident, fname)) = self.pending.pop() {
+            let program = self.program;
+            if let Some(func) = program
+                .modules
+                .get(&mident)
+                .and_then(|mdef| mdef.functions.get(&fname))
+            {
+                self.visit_function(func);
+            }
+        }
+    }
+
+    fn visit_base_type(&mut self, ty: &BaseType) {
+        if let BaseType_::Apply(_, typename, type_args) = &ty.value {
+            if let TypeName_::ModuleType(mident, _) = &typename.value {
+                self.add_module(*mident);
+            }
+            for type_arg in type_args.iter() {
+                self.visit_base_type(type_arg);
+            }
+        }
+    }
+
+    fn visit_single_type(&mut self, ty: &SingleType) {
+        match &ty.value {
+            SingleType_::Base(base) | SingleType_::Ref(_, base) => self.visit_base_type(base),
+        }
+    }
+
+    fn visit_type(&mut self, ty: &Type) {
+        match &ty.value {
+            Type_::Unit => {}
+            Type_::Single(single) => self.visit_single_type(single),
+            Type_::Multiple(singles) => singles.iter().for_each(|s| self.visit_single_type(s)),
+        }
+    }
+
+    fn visit_function(&mut self, func: &Function) {
+        for (_, ty) in func.signature.parameters.iter() {
+            self.visit_single_type(ty);
+        }
+        self.visit_type(&func.signature.return_type);
+        if let FunctionBody_::Defined { locals, body } = &func.body.value {
+            for (_, ty) in locals.key_cloned_iter() {
+                self.visit_single_type(ty);
+            }
+            self.visit_block(body);
+        }
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        for stmt in block.iter() {
+            self.visit_statement(stmt);
+        }
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement) {
+        use Statement_ as S;
+        match &stmt.value {
+            S::Command(cmd) => self.visit_command(cmd),
+            S::IfElse {
+                cond,
+                if_block,
+                else_block,
+            } => {
+                self.visit_exp(cond);
+                self.visit_block(if_block);
+                self.visit_block(else_block);
+            }
+            S::While {
+                cond: (pre_block, cond),
+                block,
+            } => {
+                self.visit_block(pre_block);
+                self.visit_exp(cond);
+                self.visit_block(block);
+            }
+            S::Loop { block, .. } => self.visit_block(block),
+        }
+    }
+
+    fn visit_command(&mut self, cmd: &Command) {
+        use Command_ as C;
+        match &cmd.value {
+            C::Assign(lvalues, rhs) => {
+                for lvalue in lvalues.iter() {
+                    self.visit_lvalue(lvalue);
+                }
+                self.visit_exp(rhs);
+            }
+            C::Mutate(lhs, rhs) => {
+                self.visit_exp(lhs);
+                self.visit_exp(rhs);
+            }
+            C::Abort(exp)
+            | C::Return { exp, .. }
+            | C::IgnoreAndPop { exp, .. }
+            | C::JumpIf { cond: exp, .. } => self.visit_exp(exp),
+            C::Break | C::Continue | C::Jump { .. } => {}
+        }
+    }
+
+    fn visit_lvalue(&mut self, lvalue: &LValue) {
+        match &lvalue.value {
+            LValue_::Ignore => {}
+            LValue_::Var(_, ty) => self.visit_single_type(ty),
+            LValue_::Unpack(_, type_args, fields) => {
+                for type_arg in type_args.iter() {
+                    self.visit_base_type(type_arg);
+                }
+                for (_, field) in fields.iter() {
+                    self.visit_lvalue(field);
+                }
+            }
+        }
+    }
+
+    fn visit_exp(&mut self, exp: &Exp) {
+        use UnannotatedExp_ as E;
+        self.visit_type(&exp.ty);
+        match &exp.exp.value {
+            E::ModuleCall(call) => {
+                self.add_function(call.module, call.name);
+                for type_arg in call.type_arguments.iter() {
+                    self.visit_base_type(type_arg);
+                }
+                self.visit_exp(&call.arguments);
+            }
+            E::Builtin(builtin, args) => {
+                use BuiltinFunction_ as F;
+                match &builtin.value {
+                    F::MoveTo(ty) | F::MoveFrom(ty) | F::BorrowGlobal(_, ty) | F::Exists(ty) => {
+                        self.visit_base_type(ty)
+                    }
+                }
+                self.visit_exp(args);
+            }
+            E::Vector(_, _, element_ty, elems) => {
+                self.visit_base_type(element_ty);
+                self.visit_exp(elems);
+            }
+            E::Freeze(e)
+            | E::Dereference(e)
+            | E::UnaryExp(_, e)
+            | E::Borrow(_, e, _)
+            | E::Cast(e, _) => self.visit_exp(e),
+            E::BinopExp(lhs, _, rhs) => {
+                self.visit_exp(lhs);
+                self.visit_exp(rhs);
+            }
+            E::Pack(_, type_args, fields) => {
+                for type_arg in type_args.iter() {
+                    self.visit_base_type(type_arg);
+                }
+                for (_, ty, e) in fields.iter() {
+                    self.visit_base_type(ty);
+                    self.visit_exp(e);
+                }
+            }
+            E::ExpList(items) => {
+                for item in items.iter() {
+                    match item {
+                        ExpListItem::Single(e, ty) => {
+                            self.visit_exp(e);
+                            self.visit_single_type(ty);
+                        }
+                        ExpListItem::Splat(_, e, tys) => {
+                            self.visit_exp(e);
+                            tys.iter().for_each(|ty| self.visit_single_type(ty));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// --prune: keeps the modules of `root_package` whole, and only the modules and functions of
+/// dependencies that are reachable from them (or from its scripts) through calls, struct fields
+/// and type arguments
+pub fn prune_program(program: &Program, root_package: Symbol) -> Program {
+    let mut reachability = Reachability::new(program);
+    for (mident, mdef) in program.modules.key_cloned_iter() {
+        if mdef.package_name == Some(root_package) {
+            // modules with structs or constants only have no function to be reached through
+            reachability.add_module(mident);
+            for (fname, _) in mdef.functions.key_cloned_iter() {
+                reachability.add_function(mident, fname);
+            }
+        }
+    }
+    for script in program.scripts.values() {
+        if script.package_name == Some(root_package) {
+            reachability.visit_function(&script.function);
+        }
+    }
+    reachability.run();
+
+    let mut modules = UniqueMap::new();
+    for (mident, mdef) in program.modules.key_cloned_iter() {
+        if !reachability.modules.contains(&mident) {
+            continue;
+        }
+        let mut functions = UniqueMap::new();
+        for (fname, func) in mdef.functions.key_cloned_iter() {
+            if reachability.functions.contains(&(mident, fname)) {
+                functions.add(fname, func.clone()).unwrap();
+            }
+        }
+        let mdef = ModuleDefinition {
+            functions,
+            ..mdef.clone()
+        };
+        modules.add(mident, mdef).unwrap();
+    }
+    Program {
+        modules,
+        scripts: program.scripts.clone(),
+    }
+}
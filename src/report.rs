@@ -0,0 +1,151 @@
+use crate::shared::MoveToTsOptions;
+use codespan_reporting::diagnostic::Severity;
+use move_compiler::diagnostics::{
+    codes::DiagnosticInfo, report_diagnostics_to_color_buffer, Diagnostics, FilesSourceText,
+};
+use move_ir_types::location::Loc;
+use serde_json::{json, Value};
+use std::io::Write;
+
+/// Values of --message-format
+pub const MESSAGE_FORMAT_HUMAN: &str = "human";
+pub const MESSAGE_FORMAT_JSON: &str = "json";
+pub const MESSAGE_FORMAT_VALUES: &[&str] = &[MESSAGE_FORMAT_HUMAN, MESSAGE_FORMAT_JSON];
+
+pub fn is_json(config: &MoveToTsOptions) -> bool {
+    config.message_format() == MESSAGE_FORMAT_JSON
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Bug => "bug",
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note => "note",
+        Severity::Help => "help",
+    }
+}
+
+/// Our own diagnostics get stable names; compiler diagnostics get the code the compiler renders
+/// (e.g. E03002 for an unbound module)
+fn diagnostic_code(info: &DiagnosticInfo) -> String {
+    let (code, message) = info.clone().render();
+    match message {
+        "Not Translatable" => "NotTranslatable".to_string(),
+        "Skipped Generation" => "SkippedGeneration".to_string(),
+        _ => code,
+    }
+}
+
+/// 1-based line and column of a byte offset
+fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = source.get(..offset).unwrap_or(source);
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+fn span_to_json(files: &FilesSourceText, loc: Loc) -> (Value, Value) {
+    match files.get(&loc.file_hash()) {
+        None => (Value::Null, Value::Null),
+        Some((fname, source)) => {
+            let (line_start, column_start) = line_col(source, loc.start() as usize);
+            let (line_end, column_end) = line_col(source, loc.end() as usize);
+            let span = json!({
+                "byte_start": loc.start(),
+                "byte_end": loc.end(),
+                "line_start": line_start,
+                "column_start": column_start,
+                "line_end": line_end,
+                "column_end": column_end,
+            });
+            (json!(fname.to_string()), span)
+        }
+    }
+}
+
+pub fn diagnostics_to_json(files: &FilesSourceText, diags: Diagnostics) -> Vec<Value> {
+    diags
+        .into_vec()
+        .into_iter()
+        .filter_map(|diag| {
+            let code = diagnostic_code(diag.info());
+            // the labels are only exposed in codespan format
+            let mut single = Diagnostics::new();
+            single.add(diag);
+            let (severity, _message, (loc, msg), secondary, notes) =
+                single.into_codespan_format().pop()?;
+            let (file, span) = span_to_json(files, loc);
+            let labels = secondary
+                .into_iter()
+                .map(|(loc, msg)| {
+                    let (file, span) = span_to_json(files, loc);
+                    json!({ "file": file, "span": span, "message": msg })
+                })
+                .collect::<Vec<_>>();
+            Some(json!({
+                "severity": severity_name(severity),
+                "code": code,
+                "message": msg,
+                "file": file,
+                "span": span,
+                "labels": labels,
+                "notes": notes,
+            }))
+        })
+        .collect()
+}
+
+/// Prints diagnostics in the format selected by --message-format: rendered to stderr the same
+/// way the compiler does, or as one JSON object per line on stdout
+pub fn report_diagnostics(files: &FilesSourceText, diags: Diagnostics, config: &MoveToTsOptions) {
+    if diags.is_empty() {
+        return;
+    }
+    if is_json(config) {
+        for diag in diagnostics_to_json(files, diags) {
+            println!("{}", diag);
+        }
+    } else {
+        let buffer = report_diagnostics_to_color_buffer(files, diags);
+        std::io::stderr()
+            .write_all(&buffer)
+            .expect("Failed to write diagnostics");
+    }
+}
+
+fn print_json_message(severity: &str, message: &str, notes: &[String]) {
+    let diag = json!({
+        "severity": severity,
+        "code": Value::Null,
+        "message": message,
+        "file": Value::Null,
+        "span": Value::Null,
+        "labels": [],
+        "notes": notes,
+    });
+    println!("{}", diag);
+}
+
+/// Same as `report_diagnostics`, for errors that do not come with a source location
+pub fn report_message(message: &str, config: &MoveToTsOptions) {
+    if is_json(config) {
+        print_json_message("error", message, &[]);
+    } else {
+        eprintln!("{}", message);
+    }
+}
+
+/// Progress of a run, e.g. removed or out-of-date files. `details` (such as a diff) are printed
+/// after the message, or become the notes of the JSON object.
+pub fn report_note(message: &str, details: Option<&str>, config: &MoveToTsOptions) {
+    if is_json(config) {
+        let notes = details.map(|d| vec![d.to_string()]).unwrap_or_default();
+        print_json_message("note", message, &notes);
+    } else {
+        println!("{}", message);
+        if let Some(details) = details {
+            print!("{}", details);
+        }
+    }
+}
@@ -1,10 +1,12 @@
+use crate::config::ExternalPackage;
+use crate::report::MESSAGE_FORMAT_VALUES;
 use crate::tsgen_writer::TsgenWriter;
 use clap::Parser;
 use itertools::Itertools;
 use move_compiler::{
     diagnostics::{
         codes::{Category, DiagnosticCode, Severity},
-        Diagnostic,
+        Diagnostic, Diagnostics,
     },
     expansion::ast::{Address, Attribute, AttributeValue_, Attribute_, ModuleIdent},
     hlir::ast::*,
@@ -16,7 +18,7 @@ use move_ir_types::location::Loc;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::path::PathBuf;
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Copy, Clone)]
 pub struct NotTranslatable {}
@@ -32,6 +34,21 @@ impl DiagnosticCode for NotTranslatable {
     }
 }
 
+/// Parts of the output that were skipped without failing the run
+#[derive(Copy, Clone)]
+pub struct SkippedGeneration {}
+
+impl DiagnosticCode for SkippedGeneration {
+    const CATEGORY: Category = Category::TypeSafety;
+    fn severity(self) -> Severity {
+        Severity::Warning
+    }
+
+    fn code_and_message(self) -> (u8, &'static str) {
+        (2, "Skipped Generation")
+    }
+}
+
 macro_rules! derr {
     ($primary: expr $(,)?) => {{
         Err(Diagnostic::new(
@@ -74,19 +91,141 @@ pub struct MoveToTsOptions {
     /// generate #[test] functions
     #[clap(long = "test-address", short = 't', default_value = "")]
     pub test_address: String,
-    #[clap(long = "gen-cli", short = 'c')]
+    #[clap(long = "gen-cli", short = 'c', overrides_with = "no_cli")]
     pub cli: bool,
-    #[clap(long = "gen-ui", short = 'u')]
+    #[clap(long = "no-gen-cli", hide = true, overrides_with = "cli")]
+    pub no_cli: bool,
+    #[clap(long = "gen-ui", short = 'u', overrides_with = "no_ui")]
     pub ui: bool,
+    #[clap(long = "no-gen-ui", hide = true, overrides_with = "ui")]
+    pub no_ui: bool,
     // default to synchronous functions but allow synchronous version to be output as well
-    #[clap(long = "asynchronous", short = 'a')]
+    #[clap(long = "asynchronous", short = 'a', overrides_with = "no_asynchronous")]
     pub asynchronous: bool,
+    #[clap(long = "no-asynchronous", hide = true, overrides_with = "asynchronous")]
+    pub no_asynchronous: bool,
     /// generate package.json
     #[clap(long = "package-json-name", short = 'n', default_value = "")]
     pub package_json_name: String,
+    /// version written to package.json (default: the version of an existing package.json, or
+    /// 0.0.1)
+    #[clap(long = "package-version", default_value = "")]
+    pub package_version: String,
+    /// description written to package.json
+    #[clap(long = "package-description", default_value = "")]
+    pub package_description: String,
+    /// pin a dependency of package.json, e.g. aptos=1.3.16 (repeatable, or comma-separated)
+    #[clap(long = "dependency")]
+    pub dependency: Vec<String>,
+    /// add or pin a devDependency of package.json, e.g. typescript=^4.8.4 (repeatable, or
+    /// comma-separated)
+    #[clap(long = "dev-dependency")]
+    pub dev_dependency: Vec<String>,
+    /// paths jest skips when running generated tests, replacing the defaults (repeatable)
+    #[clap(long = "test-path-ignore-patterns")]
+    pub test_path_ignore_patterns: Vec<String>,
+    /// keep running and regenerate whenever a Move source of the package or its dependencies
+    /// changes
+    #[clap(long = "watch", short = 'w')]
+    pub watch: bool,
+    /// do not write anything; fail if the generated files in the output path are out of date
+    #[clap(long = "check")]
+    pub check: bool,
+    /// run the full compilation pipeline and report all errors and warnings before generating
+    #[clap(long = "strict-compile", overrides_with = "no_strict_compile")]
+    pub strict_compile: bool,
+    #[clap(
+        long = "no-strict-compile",
+        hide = true,
+        overrides_with = "strict_compile"
+    )]
+    pub no_strict_compile: bool,
+    /// keep going after untranslatable functions: stub their bodies with a runtime error and
+    /// report all errors at the end
+    #[clap(long = "lenient", overrides_with = "no_lenient")]
+    pub lenient: bool,
+    #[clap(long = "no-lenient", hide = true, overrides_with = "lenient")]
+    pub no_lenient: bool,
+    /// how to print errors and warnings: human (default), or json (one object per line on stdout)
+    #[clap(long = "message-format", possible_values = MESSAGE_FORMAT_VALUES)]
+    pub message_format: Option<String>,
+    /// only generate the modules and functions of dependencies that are reachable from the root
+    /// package
+    #[clap(long = "prune", overrides_with = "no_prune")]
+    pub prune: bool,
+    #[clap(long = "no-prune", hide = true, overrides_with = "prune")]
+    pub no_prune: bool,
+    /// only generate the API surface (structs, parsers, type tags, payload builders): bodies of
+    /// non-entry functions are replaced by a runtime error
+    #[clap(long = "surface-only", overrides_with = "no_surface_only")]
+    pub surface_only: bool,
+    #[clap(long = "no-surface-only", hide = true, overrides_with = "surface_only")]
+    pub no_surface_only: bool,
+    /// module system of the generated code: commonjs (default), esm, or dual (both, from one
+    /// build)
+    #[clap(long = "module", possible_values = MODULE_VALUES)]
+    pub module: Option<String>,
+    /// integers of the generated code: boxed (default, U8 ... U256 objects), or bigint (native
+    /// bigints, range-checked inline)
+    #[clap(long = "numbers", possible_values = NUMBERS_VALUES)]
+    pub numbers: Option<String>,
+    /// write a .ts.map next to each generated module, mapping it back to the Move source
+    #[clap(long = "source-map", overrides_with = "no_source_map")]
+    pub source_map: bool,
+    #[clap(long = "no-source-map", hide = true, overrides_with = "source_map")]
+    pub no_source_map: bool,
+    /// generate from compiled modules (.mv files, or directories containing them) instead of
+    /// a source package. Function bodies are not available and throw when called.
+    #[clap(long = "bytecode", parse(from_os_str))]
+    pub bytecode: Vec<PathBuf>,
+    /// name the address of bytecode modules, e.g. aptos_framework=0x1 (default: the hex address)
+    #[clap(long = "bytecode-address")]
+    pub bytecode_address: Vec<String>,
+    /// additional named addresses, e.g. my_dex=0xabc (repeatable, or comma-separated)
+    #[clap(long = "named-addresses")]
+    pub named_addresses: Vec<String>,
+    /// compile in dev mode, using [dev-addresses] and [dev-dependencies]
+    #[clap(long = "dev", overrides_with = "no_dev_mode")]
+    pub dev_mode: bool,
+    #[clap(long = "no-dev", hide = true, overrides_with = "dev_mode")]
+    pub no_dev_mode: bool,
+    /// compile in test mode
+    #[clap(long = "test", overrides_with = "no_test_mode")]
+    pub test_mode: bool,
+    #[clap(long = "no-test", hide = true, overrides_with = "test_mode")]
+    pub no_test_mode: bool,
+    /// do not fetch the latest version of git dependencies (e.g. when offline)
+    #[clap(
+        long = "skip-fetch-latest-git-deps",
+        overrides_with = "no_skip_fetch_latest_git_deps"
+    )]
+    pub skip_fetch_latest_git_deps: bool,
+    #[clap(
+        long = "no-skip-fetch-latest-git-deps",
+        hide = true,
+        overrides_with = "skip_fetch_latest_git_deps"
+    )]
+    pub no_skip_fetch_latest_git_deps: bool,
+    /// installation directory for build artifacts, and the default output path
+    #[clap(long = "install-dir", parse(from_os_str))]
+    pub install_dir: Option<PathBuf>,
+    /// import a Move package from a prebuilt npm module instead of generating it, e.g.
+    /// AptosFramework=@manahippo/aptos-framework-ts (repeatable, or comma-separated)
+    #[clap(long = "external-package")]
+    pub external_package: Vec<String>,
+    /// only generate modules matching these globs (address::module, or package name)
+    #[clap(long = "include")]
+    pub include: Vec<String>,
+    /// skip modules matching these globs (address::module, or package name)
+    #[clap(long = "exclude")]
+    pub exclude: Vec<String>,
+    /// also generate the framework modules excluded by default (secp256k1, ristretto255), whose
+    /// natives throw when called
+    #[clap(long = "no-default-excludes")]
+    pub no_default_excludes: bool,
 }
 
-use crate::utils::{capitalize, rename};
+use crate::utils::{capitalize, rename, MODULE_VALUES, NUMBERS_BIGINT, NUMBERS_VALUES};
 pub(crate) use derr;
 use move_command_line_common::address::NumericalAddress;
 
@@ -98,13 +237,17 @@ pub struct CmdParams {
 }
 
 pub struct Context {
-    pub program: Rc<Program>,
+    pub program: Arc<Program>,
     pub current_module: Option<ModuleIdent>,
     pub current_function_signature: Option<FunctionSignature>,
     // modules imported from same package
     pub same_package_imports: BTreeSet<String>,
     // external packages imported
     pub package_imports: BTreeSet<String>,
+    // packages imported from prebuilt npm modules: (package, npm module)
+    pub external_imports: BTreeSet<(String, String)>,
+    // --external-package, keyed by Move package name
+    pub external_packages: BTreeMap<String, ExternalPackage>,
     // all modules
     pub visited_modules: BTreeSet<ModuleIdent>,
     // external packages imported
@@ -132,6 +275,14 @@ pub struct Context {
     )>,
     // all show_iter_table directives collected
     pub all_shows_iter_tables: Vec<(ModuleIdent, StructName, StructDefinition, Name)>,
+    // all method directives collected
+    pub methods: Vec<(ModuleIdent, StructName, Name)>,
+    // modules filtered out by --include/--exclude, which must not be referenced
+    pub excluded_modules: BTreeSet<ModuleIdent>,
+    // errors recovered from in --lenient mode, reported once generation is done
+    pub diagnostics: Diagnostics,
+    // non-fatal diagnostics, e.g. parts of the cli that had to be skipped
+    pub warnings: Diagnostics,
 }
 
 pub fn is_same_package(a1: Address, a2: Address) -> bool {
@@ -157,13 +308,16 @@ pub fn is_same_module(mi1: &ModuleIdent, mi2: &ModuleIdent) -> bool {
 }
 
 impl Context {
-    pub fn new(config: &MoveToTsOptions, program: Rc<Program>) -> Self {
+    pub fn new(config: &MoveToTsOptions, program: Arc<Program>) -> Self {
         Self {
             program,
             current_module: None,
             current_function_signature: None,
             same_package_imports: BTreeSet::new(),
             package_imports: BTreeSet::new(),
+            external_imports: BTreeSet::new(),
+            // validated before generation starts
+            external_packages: config.external_packages().unwrap_or_default(),
             visited_modules: BTreeSet::new(),
             visited_packages: BTreeMap::new(),
             config: config.clone(),
@@ -172,13 +326,33 @@ impl Context {
             queries: vec![],
             printer_methods: vec![],
             all_shows_iter_tables: vec![],
+            methods: vec![],
+            excluded_modules: BTreeSet::new(),
+            diagnostics: Diagnostics::new(),
+            warnings: Diagnostics::new(),
         }
     }
 
+    /// Folds in what a context that translated other modules collected. Called in module order,
+    /// so that the result does not depend on how modules were scheduled.
+    pub fn merge(&mut self, other: Context) {
+        self.visited_modules.extend(other.visited_modules);
+        self.visited_packages.extend(other.visited_packages);
+        self.cmds.extend(other.cmds);
+        self.queries.extend(other.queries);
+        self.printer_methods.extend(other.printer_methods);
+        self.all_shows_iter_tables
+            .extend(other.all_shows_iter_tables);
+        self.methods.extend(other.methods);
+        self.diagnostics.extend(other.diagnostics);
+        self.warnings.extend(other.warnings);
+    }
+
     pub fn reset_for_module(&mut self, mname: ModuleIdent) {
         self.current_module = Some(mname);
         self.same_package_imports.clear();
         self.package_imports.clear();
+        self.external_imports.clear();
         self.tests.clear();
         // additive
         self.visited_modules.insert(mname);
@@ -193,6 +367,12 @@ impl Context {
         )
     }
 
+    /// The npm module that `mident` is imported from, if its package is external
+    pub fn external_package(&self, mident: &ModuleIdent) -> Option<&ExternalPackage> {
+        let package_name = self.program.modules.get(mident)?.package_name?;
+        self.external_packages.get(package_name.as_str())
+    }
+
     pub fn is_current_module(&self, other: &ModuleIdent) -> bool {
         self.current_module.unwrap() == *other
     }
@@ -205,6 +385,10 @@ impl Context {
         self.package_imports.insert(modname);
     }
 
+    pub fn add_external_import(&mut self, package_name: String, npm_module: String) {
+        self.external_imports.insert((package_name, npm_module));
+    }
+
     pub fn get_tparam_index(&self, tparam: &TParam) -> Option<usize> {
         match &self.current_function_signature {
             None => None,
@@ -272,9 +456,17 @@ impl Context {
             .push((*mi, *sname, sdef.clone(), *field_name));
     }
 
+    pub fn add_method(&mut self, mi: &ModuleIdent, sname: &StructName, fname: &Name) {
+        self.methods.push((*mi, *sname, *fname));
+    }
+
     pub fn is_async(&self) -> bool {
         self.config.asynchronous
     }
+
+    pub fn is_bigint(&self) -> bool {
+        self.config.numbers() == NUMBERS_BIGINT
+    }
 }
 
 pub trait AstTsPrinter {
@@ -351,6 +543,24 @@ pub fn format_address_hex(address: Address) -> String {
     }
 }
 
+/// The named address an address was declared with, which is its key in the generated addresses.ts
+pub fn address_name(address: Address) -> Option<String> {
+    match address {
+        Address::Numerical(Some(name), _) => Some(name.value.to_string()),
+        Address::Numerical(None, _) => None,
+        Address::NamedUnassigned(name) => Some(name.value.to_string()),
+    }
+}
+
+/// A HexString expression for an address. Named addresses are looked up in the address registry
+/// at runtime, so they follow setAddresses
+pub fn ts_address(address: Address) -> String {
+    match address_name(address) {
+        Some(name) => format!("$addresses.addressOf({})", quote(&name)),
+        None => format!("new HexString({})", quote(&format_address_hex(address))),
+    }
+}
+
 pub fn ts_format_numerical_address(numerical: &NumericalAddress) -> TermResult {
     Ok(format!(
         "new HexString(\"{}\")",
@@ -358,41 +568,64 @@ pub fn ts_format_numerical_address(numerical: &NumericalAddress) -> TermResult {
     ))
 }
 
-pub fn ts_format_address_as_literal(addr: &Address, loc: Loc) -> TermResult {
+pub fn ts_format_address_as_literal(addr: &Address, _loc: Loc) -> TermResult {
     /*
     e.g.:
     - new HexString("0x1")
-    - AptosFramework.address
+    - $addresses.addressOf("aptos_framework"), for addresses only assigned at runtime
      */
     match addr {
         Address::Numerical(_opt_name, numerical) => ts_format_numerical_address(&numerical.value),
-        Address::NamedUnassigned(name) => derr!((loc, format!("Unassigned address: {}", name))),
+        Address::NamedUnassigned(_) => Ok(ts_address(*addr)),
     }
 }
 
 pub fn format_qualified_name(
     mident: &ModuleIdent,
     name: &impl fmt::Display,
+    loc: Loc,
     c: &mut Context,
-) -> String {
+) -> TermResult {
+    if c.excluded_modules.contains(mident) {
+        return derr!((
+            loc,
+            format!(
+                "{}::{}::{} is referenced here, but its module is excluded from generation",
+                format_address_hex(mident.value.address),
+                mident.value.module,
+                name
+            )
+        ));
+    }
     let name = rename(name);
     if c.is_current_module(mident) {
         // name exists in same module, no qualifier needed
-        name
+        Ok(name)
+    } else if let Some(external) = c.external_package(mident) {
+        // name exists in a prebuilt npm module, which exports a namespace per package
+        let npm_module = external.npm_module.clone();
+        let package_name = format_address(mident.value.address);
+        c.add_external_import(package_name.clone(), npm_module);
+        Ok(format!(
+            "{}$ext.{}.{}",
+            capitalize(&package_name),
+            capitalize(&mident.value.module),
+            name
+        ))
     } else if c.is_current_package(mident) {
         // name exists in same package, just add module name as qualifier
         c.add_same_package_import(mident.value.module.to_string());
-        format!("{}.{}", capitalize(&mident.value.module), name)
+        Ok(format!("{}.{}", capitalize(&mident.value.module), name))
     } else {
         // name exists in a different package, use fully qualified name
         let package_name = format_address(mident.value.address);
         c.add_package_import(package_name.clone());
-        format!(
+        Ok(format!(
             "{}.{}.{}",
             capitalize(&package_name),
             capitalize(&mident.value.module),
             name
-        )
+        ))
     }
 }
 
@@ -429,13 +662,15 @@ pub fn base_type_to_typetag_builder_inner(
                 }
                 BuiltinTypeName_::Bool => Ok("AtomicTypeTag.Bool".to_string()),
                 BuiltinTypeName_::U8 => Ok("AtomicTypeTag.U8".to_string()),
+                BuiltinTypeName_::U16 => Ok("AtomicTypeTag.U16".to_string()),
+                BuiltinTypeName_::U32 => Ok("AtomicTypeTag.U32".to_string()),
                 BuiltinTypeName_::U64 => Ok("AtomicTypeTag.U64".to_string()),
                 BuiltinTypeName_::U128 => Ok("AtomicTypeTag.U128".to_string()),
+                BuiltinTypeName_::U256 => Ok("AtomicTypeTag.U256".to_string()),
                 BuiltinTypeName_::Address => Ok("AtomicTypeTag.Address".to_string()),
                 BuiltinTypeName_::Signer => Ok("AtomicTypeTag.Signer".to_string()),
             },
             TypeName_::ModuleType(mident, sname) => {
-                let address = format_address_hex(mident.value.address);
                 let modname = mident.value.module;
                 let tparams = format!(
                     "[{}]",
@@ -458,8 +693,8 @@ pub fn base_type_to_typetag_builder_inner(
                     ))
                 } else {
                     Ok(format!(
-                        "new StructTag(new HexString({}), {}, {}, {})",
-                        quote(&address),
+                        "new StructTag({}, {}, {}, {})",
+                        ts_address(mident.value.address),
                         quote(&modname),
                         quote(&sname),
                         tparams
@@ -494,13 +729,15 @@ pub fn base_type_to_typetag(base_ty: &BaseType, c: &mut Context) -> TermResult {
                 }
                 BuiltinTypeName_::Bool => Ok("AtomicTypeTag.Bool".to_string()),
                 BuiltinTypeName_::U8 => Ok("AtomicTypeTag.U8".to_string()),
+                BuiltinTypeName_::U16 => Ok("AtomicTypeTag.U16".to_string()),
+                BuiltinTypeName_::U32 => Ok("AtomicTypeTag.U32".to_string()),
                 BuiltinTypeName_::U64 => Ok("AtomicTypeTag.U64".to_string()),
                 BuiltinTypeName_::U128 => Ok("AtomicTypeTag.U128".to_string()),
+                BuiltinTypeName_::U256 => Ok("AtomicTypeTag.U256".to_string()),
                 BuiltinTypeName_::Address => Ok("AtomicTypeTag.Address".to_string()),
                 BuiltinTypeName_::Signer => Ok("AtomicTypeTag.Signer".to_string()),
             },
             TypeName_::ModuleType(mident, sname) => {
-                let address = format_address_hex(mident.value.address);
                 let modname = mident.value.module;
                 let tparams = format!("[{}]", comma_term(ss, c, base_type_to_typetag)?);
                 if c.is_current_module(mident) {
@@ -515,8 +752,8 @@ pub fn base_type_to_typetag(base_ty: &BaseType, c: &mut Context) -> TermResult {
                     ))
                 } else {
                     Ok(format!(
-                        "new StructTag(new HexString({}), {}, {}, {})",
-                        quote(&address),
+                        "new StructTag({}, {}, {}, {})",
+                        ts_address(mident.value.address),
                         quote(&modname),
                         quote(sname),
                         tparams
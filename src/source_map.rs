@@ -0,0 +1,170 @@
+use move_compiler::diagnostics::FilesSourceText;
+use move_ir_types::location::Loc;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Appends `value` as a base64 VLQ, the number encoding used in source map mappings
+fn push_vlq(out: &mut String, value: i64) {
+    let mut vlq = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64_CHARS[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+/// Absolute, lexically normalized path. The output directory may not exist yet, so this does not
+/// go through the filesystem.
+fn absolute(path: &Path) -> PathBuf {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// `to` relative to the directory `from`, both absolute
+fn relative_path(from: &Path, to: &Path) -> String {
+    let from = from.components().collect::<Vec<_>>();
+    let to = to.components().collect::<Vec<_>>();
+    let common = from
+        .iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    for component in &to[common..] {
+        relative.push(component);
+    }
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// Byte offsets at which each line of a source file starts
+fn line_starts(source: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+        .collect()
+}
+
+/// 0-based line and column of a byte offset
+fn position(source: &str, line_starts: &[usize], offset: usize) -> (usize, usize) {
+    let line = line_starts.partition_point(|start| *start <= offset) - 1;
+    let column = source
+        .get(line_starts[line]..offset)
+        .map_or(0, |prefix| prefix.chars().count());
+    (line, column)
+}
+
+/// Builds a source map (version 3) for the generated module at `ts_path`, relative to
+/// `output_root`. `line_locs` holds the Move location each line of `content` was generated from;
+/// such lines are mapped from their first non-blank column to the start of the location.
+/// Returns None if no line comes from a Move source, e.g. for modules generated from bytecode.
+pub fn generate_source_map(
+    ts_path: &Path,
+    content: &str,
+    line_locs: &[Option<Loc>],
+    files: &FilesSourceText,
+    output_root: &Path,
+) -> Option<String> {
+    let ts_path = absolute(&output_root.join(ts_path));
+    let map_dir = ts_path.parent().unwrap();
+
+    let mut sources = vec![];
+    // file hash -> index in sources, and the line starts of that file
+    let mut source_indices = BTreeMap::new();
+    let mut mappings = String::new();
+    let (mut prev_source, mut prev_line, mut prev_column) = (0, 0, 0);
+    for (idx, (line, loc)) in content.split('\n').zip(line_locs.iter()).enumerate() {
+        if idx > 0 {
+            mappings.push(';');
+        }
+        let loc = match loc {
+            Some(loc) => loc,
+            None => continue,
+        };
+        let (fname, source) = match files.get(&loc.file_hash()) {
+            Some(file) => file,
+            None => continue,
+        };
+        let (source_idx, starts) = source_indices.entry(loc.file_hash()).or_insert_with(|| {
+            sources.push(relative_path(map_dir, &absolute(Path::new(fname.as_str()))));
+            (sources.len() - 1, line_starts(source))
+        });
+        let (source_line, source_column) = position(source, starts, loc.start() as usize);
+        let generated_column = line.len() - line.trim_start().len();
+
+        push_vlq(&mut mappings, generated_column as i64);
+        push_vlq(&mut mappings, *source_idx as i64 - prev_source);
+        push_vlq(&mut mappings, source_line as i64 - prev_line);
+        push_vlq(&mut mappings, source_column as i64 - prev_column);
+        prev_source = *source_idx as i64;
+        prev_line = source_line as i64;
+        prev_column = source_column as i64;
+    }
+
+    if sources.is_empty() {
+        return None;
+    }
+    let map = json!({
+        "version": 3,
+        "file": ts_path.file_name().unwrap().to_string_lossy(),
+        "sourceRoot": "",
+        "sources": sources,
+        "names": [],
+        "mappings": mappings,
+    });
+    Some(map.to_string() + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vlq(value: i64) -> String {
+        let mut out = String::new();
+        push_vlq(&mut out, value);
+        out
+    }
+
+    #[test]
+    fn encodes_single_digits() {
+        assert_eq!(vlq(0), "A");
+        assert_eq!(vlq(1), "C");
+        assert_eq!(vlq(-1), "D");
+        assert_eq!(vlq(15), "e");
+    }
+
+    #[test]
+    fn encodes_continuation_digits() {
+        assert_eq!(vlq(16), "gB");
+        assert_eq!(vlq(-16), "hB");
+        assert_eq!(vlq(1000), "w+B");
+    }
+}
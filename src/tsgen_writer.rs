@@ -1,11 +1,23 @@
 use move_compiler::diagnostics::Diagnostic;
+use move_ir_types::location::Loc;
 use std::collections::HashSet;
 
+/// A position in the output, see `TsgenWriter::rollback`
+#[derive(Clone, Copy)]
+pub struct WriterMark {
+    lines: usize,
+    cur_len: usize,
+    margin: usize,
+}
+
 pub struct TsgenWriter {
     margin: usize,
     import_set: HashSet<String>,
     imports: Vec<String>,
     lines: Vec<String>,
+    /// the Move location each line was started under, for source maps
+    locs: Vec<Option<Loc>>,
+    loc: Option<Loc>,
 }
 
 impl Default for TsgenWriter {
@@ -21,6 +33,8 @@ impl TsgenWriter {
             import_set: HashSet::new(),
             imports: vec![],
             lines: vec![String::new()],
+            locs: vec![None],
+            loc: None,
         }
     }
 
@@ -28,6 +42,25 @@ impl TsgenWriter {
         self.lines.last_mut().unwrap()
     }
 
+    pub fn mark(&self) -> WriterMark {
+        WriterMark {
+            lines: self.lines.len(),
+            cur_len: self.lines.last().unwrap().len(),
+            margin: self.margin,
+        }
+    }
+
+    /// Discards everything written since `mark` was taken
+    pub fn rollback(&mut self, mark: WriterMark) {
+        self.lines.truncate(mark.lines);
+        self.locs.truncate(mark.lines);
+        self.cur().truncate(mark.cur_len);
+        if mark.cur_len == 0 {
+            *self.locs.last_mut().unwrap() = None;
+        }
+        self.margin = mark.margin;
+    }
+
     pub fn import(&mut self, line: String) {
         if !self.import_set.contains(&line) {
             self.import_set.insert(line.clone());
@@ -35,12 +68,36 @@ impl TsgenWriter {
         }
     }
 
+    /// Runs `f` with the lines it starts attributed to `loc`
+    pub fn with_loc<R, F: FnOnce(&mut TsgenWriter) -> R>(&mut self, loc: Loc, f: F) -> R {
+        let outer = self.loc.replace(loc);
+        let res = f(self);
+        self.loc = outer;
+        res
+    }
+
+    /// The Move location of each line of the output, in the same order as they are displayed.
+    /// Lines that were written with embedded newlines get one entry per physical line.
+    pub fn line_locs(&self) -> Vec<Option<Loc>> {
+        let mut locs = vec![];
+        for (line, loc) in self.lines.iter().zip(self.locs.iter()) {
+            locs.push(*loc);
+            locs.extend(line.matches('\n').map(|_| None));
+        }
+        locs
+    }
+
     pub fn new_line(&mut self) {
         self.lines.push(String::new());
+        self.locs.push(None);
     }
 
     pub fn write(&mut self, s: impl AsRef<str>) {
         let margin = self.margin;
+        let loc = self.loc;
+        if self.cur().is_empty() {
+            *self.locs.last_mut().unwrap() = loc;
+        }
         let cur = self.cur();
         if cur.is_empty() {
             (0..margin).for_each(|_| cur.push(' '));
@@ -1,8 +1,43 @@
+use crate::config::ExternalPackage;
+use crate::shared::{address_name, format_address_hex, quote, MoveToTsOptions};
 use itertools::Itertools;
-use move_compiler::expansion::ast::ModuleIdent;
+use move_compiler::expansion::ast::{Address, ModuleIdent};
+use move_compiler::hlir::ast::Program;
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::fmt;
 
-pub fn generate_package_json(package_name: String, cli: bool, ui: bool) -> (String, String) {
+/// Values of --module
+pub const MODULE_COMMONJS: &str = "commonjs";
+pub const MODULE_ESM: &str = "esm";
+pub const MODULE_DUAL: &str = "dual";
+pub const MODULE_VALUES: &[&str] = &[MODULE_COMMONJS, MODULE_ESM, MODULE_DUAL];
+
+/// Values of --numbers
+pub const NUMBERS_BOXED: &str = "boxed";
+pub const NUMBERS_BIGINT: &str = "bigint";
+pub const NUMBERS_VALUES: &[&str] = &[NUMBERS_BOXED, NUMBERS_BIGINT];
+
+/// Specifier for a relative import of a generated file, or of a package's index if `is_dir`.
+/// ES modules are resolved without guessing extensions, so they need the path of the compiled
+/// file; TypeScript maps the .js back to the .ts source.
+pub fn import_specifier(path: &str, is_dir: bool, module: &str) -> String {
+    if module == MODULE_COMMONJS {
+        path.to_string()
+    } else if is_dir {
+        format!("{}/index.js", path.trim_end_matches('/'))
+    } else {
+        format!("{}.js", path)
+    }
+}
+
+/// package.json for --package-json-name. --dependency pins replace the version of a generated
+/// dependency wherever it is listed, and add it to dependencies otherwise.
+pub fn generate_package_json(
+    config: &MoveToTsOptions,
+    externals: &BTreeMap<String, ExternalPackage>,
+) -> anyhow::Result<(String, String)> {
+    let (cli, ui, module) = (config.cli, config.ui, config.module());
     let ui_dependencies = r###"
     "react": "^18.1.0",
     "react-dom": "^18.2.0",
@@ -36,20 +71,87 @@ pub fn generate_package_json(package_name: String, cli: bool, ui: bool) -> (Stri
     "commander": "^9.3.0",
     "yaml": "^2.1.1",
 "###;
-    let cli_script = r###"
+    // several packages may come from the same npm module
+    let external_dependencies = externals
+        .values()
+        .map(|external| (&external.npm_module, &external.version))
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(npm_module, version)| {
+            format!(
+                "\n    {}: {},",
+                Value::from(npm_module.as_str()),
+                Value::from(version.as_str())
+            )
+        })
+        .join("");
+    let (build_script, cli_script, entry_points) = match module {
+        MODULE_ESM => (
+            "rm -rf dist; tsc -p tsconfig.json",
+            r###"
     "cli": "node dist/cli.js",
-"###;
+"###,
+            r###"
+  "type": "module",
+  "main": "dist/index.js",
+  "typings": "dist/index.d.ts",
+  "exports": {
+    ".": {
+      "types": "./dist/index.d.ts",
+      "import": "./dist/index.js"
+    }
+  },"###,
+        ),
+        MODULE_DUAL => (
+            // the ESM build is marked as such, since the package itself is CommonJS
+            r###"rm -rf dist; tsc -p tsconfig.json && tsc -p tsconfig.cjs.json && node -e \"require('fs').writeFileSync('dist/esm/package.json', JSON.stringify({type: 'module'}))\""###,
+            r###"
+    "cli": "node dist/cjs/cli.js",
+"###,
+            r###"
+  "main": "dist/cjs/index.js",
+  "module": "dist/esm/index.js",
+  "typings": "dist/esm/index.d.ts",
+  "exports": {
+    ".": {
+      "types": "./dist/esm/index.d.ts",
+      "import": "./dist/esm/index.js",
+      "require": "./dist/cjs/index.js"
+    }
+  },"###,
+        ),
+        _ => (
+            "rm -rf dist; tsc -p tsconfig.json",
+            r###"
+    "cli": "node dist/cli.js",
+"###,
+            r###"
+  "main": "dist/index.js",
+  "typings": "dist/index.d.ts","###,
+        ),
+    };
+    let version = if config.package_version.is_empty() {
+        "0.0.1"
+    } else {
+        config.package_version.as_str()
+    };
+    let description = if config.package_description.is_empty() {
+        "".to_string()
+    } else {
+        format!(
+            "\n  \"description\": {},",
+            serde_json::to_string(&config.package_description)?
+        )
+    };
     let content = format!(
         r###"
 {{
-  "name": "{}",
-  "version": "0.0.1",
+  "name": {},
+  "version": {},{}
   "scripts": {{
-    "build": "rm -rf dist; tsc -p tsconfig.json",{}{}
+    "build": "{}",{}{}
     "test": "jest"
-  }},
-  "main": "dist/index.js",
-  "typings": "dist/index.d.ts",
+  }},{}
   "files": [ "src", "dist" ],{}
   "devDependencies": {{
     "@types/jest": "^27.4.1",
@@ -66,53 +168,140 @@ pub fn generate_package_json(package_name: String, cli: bool, ui: bool) -> (Stri
   }},
   "dependencies": {{
     "aptos": "1.3.10",
-    "big-integer": "^1.6.51",{}
-    "@manahippo/move-to-ts": "^0.2.10"
+    "big-integer": "^1.6.51",{}{}
+    "@manahippo/move-to-ts": "^0.2.11"
   }}
 }}
 "###,
-        package_name,
+        serde_json::to_string(&config.package_json_name)?,
+        serde_json::to_string(version)?,
+        description,
+        build_script,
         if ui { ui_scripts } else { "" },
         if cli { cli_script } else { "" },
+        entry_points,
         if ui { ui_browser_list } else { "" },
         if ui { ui_dependencies } else { "" },
         if cli { cli_dependencies } else { "" },
+        external_dependencies,
     );
-    ("package.json".to_string(), content)
+
+    let mut package: Value = serde_json::from_str(&content)?;
+    for (name, version) in config.dependency_pins()? {
+        let section = if package["devDependencies"].get(&name).is_some() {
+            "devDependencies"
+        } else {
+            "dependencies"
+        };
+        package[section][name] = Value::String(version);
+    }
+    for (name, version) in config.dev_dependency_pins()? {
+        package["devDependencies"][name] = Value::String(version);
+    }
+    Ok((
+        "package.json".to_string(),
+        serde_json::to_string_pretty(&package)? + "\n",
+    ))
 }
 
-pub fn generate_ts_config() -> (String, String) {
-    let content = r###"
-{
-  "compilerOptions": {
-    "target": "es2016",
-    "module": "commonjs",
+/// tsconfig.json, plus tsconfig.cjs.json for the CommonJS half of a dual build. bigint literals
+/// need es2020.
+pub fn generate_ts_config(module: &str, numbers: &str) -> Vec<(String, String)> {
+    let (target, module_kind, out_dir) = match module {
+        MODULE_ESM => ("es2020", "es2020", "./dist"),
+        MODULE_DUAL => ("es2020", "es2020", "./dist/esm"),
+        _ if numbers == NUMBERS_BIGINT => ("es2020", "commonjs", "./dist"),
+        _ => ("es2016", "commonjs", "./dist"),
+    };
+    let content = format!(
+        r###"
+{{
+  "compilerOptions": {{
+    "target": "{}",
+    "module": "{}",
     "rootDir": "./src",
     "moduleResolution": "node",
     "jsx": "react-jsx",
     "declaration": true,
     "declarationMap": true,
     "sourceMap": true,
-    "outDir": "./dist",
+    "outDir": "{}",
     "esModuleInterop": true,
     "forceConsistentCasingInFileNames": true,
     "strict": true,
     "skipLibCheck": true
+  }}
+}}
+"###,
+        target, module_kind, out_dir
+    );
+    let mut files = vec![("tsconfig.json".to_string(), content)];
+
+    if module == MODULE_DUAL {
+        let cjs_content = r###"
+{
+  "extends": "./tsconfig.json",
+  "compilerOptions": {
+    "module": "commonjs",
+    "outDir": "./dist/cjs"
   }
 }
 "###;
-    ("tsconfig.json".to_string(), content.to_string())
+        files.push(("tsconfig.cjs.json".to_string(), cjs_content.to_string()));
+    }
+    files
 }
 
-pub fn generate_jest_config() -> (String, String) {
-    let content = r###"
-module.exports = {
+/// Tests jest skips unless --test-path-ignore-patterns is given: Aptos framework modules whose tests
+/// cannot run outside the VM
+const DEFAULT_TEST_PATH_IGNORE_PATTERNS: &[&str] = &[
+    "aptos_framework/voting",
+    "aptos_framework/stake",
+    "aptos_std/signature",
+    "aptos_framework/bucket_table",
+];
+
+pub fn generate_jest_config(
+    module: &str,
+    test_path_ignore_patterns: &[String],
+) -> (String, String) {
+    // tests run through ts-jest as CommonJS; .js specifiers are mapped back to the .ts sources
+    let module_name_mapper = if module == MODULE_COMMONJS {
+        ""
+    } else {
+        r###"
+  moduleNameMapper: { "^(\\.{1,2}/.*)\\.js$": "$1" },"###
+    };
+    // the build output is never tested
+    let ignore_patterns = std::iter::once("dist/*")
+        .chain(if test_path_ignore_patterns.is_empty() {
+            DEFAULT_TEST_PATH_IGNORE_PATTERNS.to_vec()
+        } else {
+            test_path_ignore_patterns
+                .iter()
+                .map(String::as_str)
+                .collect()
+        })
+        // escaped, since patterns are regular expressions full of backslashes
+        .map(Value::from)
+        .join(", ");
+    let content = format!(
+        r###"
+module.exports = {{
   preset: "ts-jest",
   testEnvironment: "node",
-  testPathIgnorePatterns: ["dist/*", "aptos_framework/voting", "aptos_framework/stake", "aptos_std/signature", "aptos_framework/bucket_table"],
-};
-"###;
-    ("jest.config.js".to_string(), content.to_string())
+  testPathIgnorePatterns: [{}],{}
+}};
+"###,
+        ignore_patterns, module_name_mapper
+    );
+    // .js files are ES modules in a "type": "module" package
+    let filename = if module == MODULE_ESM {
+        "jest.config.cjs"
+    } else {
+        "jest.config.js"
+    };
+    (filename.to_string(), content)
 }
 
 /*
@@ -148,15 +337,31 @@ pub fn capitalize(name: &impl fmt::Display) -> String {
     }
 }
 
-pub fn generate_index(package_name: &String, modules: &[&ModuleIdent]) -> (String, String) {
+/// Parsers of builtin types, registered after those of the generated structs
+fn add_default_parsers(numbers: &str) -> &'static str {
+    if numbers == NUMBERS_BIGINT {
+        "  repo.addDefaultParsers();\n  repo.addBigIntParsers();"
+    } else {
+        "  repo.addDefaultParsers();"
+    }
+}
+
+pub fn generate_index(
+    package_name: &String,
+    modules: &[&ModuleIdent],
+    module: &str,
+    numbers: &str,
+) -> (String, String) {
     let filename = format!("{}/index.ts", package_name);
+    let specifier =
+        |mi: &ModuleIdent| import_specifier(&format!("./{}", mi.value.module), false, module);
     let exports = modules
         .iter()
         .map(|mi| {
             format!(
-                "export * as {} from './{}';\n",
+                "export * as {} from '{}';\n",
                 capitalize(&mi.value.module),
-                mi.value.module
+                specifier(mi)
             )
         })
         .collect::<Vec<_>>()
@@ -166,9 +371,9 @@ pub fn generate_index(package_name: &String, modules: &[&ModuleIdent]) -> (Strin
         .iter()
         .map(|mi| {
             format!(
-                "import * as {} from './{}';\n",
+                "import * as {} from '{}';\n",
                 capitalize(&mi.value.module),
-                mi.value.module
+                specifier(mi)
             )
         })
         .collect::<Vec<_>>()
@@ -212,7 +417,7 @@ export function loadParsers(repo: AptosParserRepo) {{
 export function getPackageRepo(): AptosParserRepo {{
   const repo = new AptosParserRepo();
   loadParsers(repo);
-  repo.addDefaultParsers();
+{}
   return repo;
 }}
 
@@ -233,29 +438,67 @@ export class App {{
   }}
 }}
 "###,
-        imports, exports, loads, app_fields, app_field_inits
+        imports,
+        exports,
+        loads,
+        add_default_parsers(numbers),
+        app_fields,
+        app_field_inits
     );
 
     (filename, content)
 }
 
-pub fn generate_topmost_index(packages: &[&String]) -> (String, String) {
+/// `externals` are packages imported from npm modules: their parsers are loaded from there
+pub fn generate_topmost_index(
+    packages: &[&String],
+    externals: &BTreeMap<String, ExternalPackage>,
+    module: &str,
+    numbers: &str,
+) -> (String, String) {
     let filename = "index.ts".to_string();
+    let specifier =
+        |package_name: &String| import_specifier(&format!("./{}", package_name), true, module);
     let exports = packages
         .iter()
-        .map(|package_name| format!("export * as {} from './{}';\n", package_name, package_name))
+        .map(|package_name| {
+            format!(
+                "export * as {} from '{}';\n",
+                package_name,
+                specifier(package_name)
+            )
+        })
         .collect::<Vec<_>>()
         .join("");
 
     let imports = packages
         .iter()
-        .map(|package_name| format!("import * as {} from './{}';\n", package_name, package_name))
+        .map(|package_name| {
+            format!(
+                "import * as {} from '{}';\n",
+                package_name,
+                specifier(package_name)
+            )
+        })
+        .chain(externals.iter().map(|(package_name, external)| {
+            format!(
+                "import {{ {} as {}$ext }} from '{}';\n",
+                package_name,
+                capitalize(package_name),
+                external.npm_module
+            )
+        }))
         .collect::<Vec<_>>()
         .join("");
 
     let loads = packages
         .iter()
         .map(|p| format!("  {}.loadParsers(repo);", p))
+        .chain(
+            externals
+                .keys()
+                .map(|p| format!("  {}$ext.loadParsers(repo);", capitalize(p))),
+        )
         .join("\n");
 
     let app_fields = packages
@@ -279,11 +522,12 @@ import {{ AptosClient }} from "aptos";
 import {{ AptosParserRepo, AptosLocalCache }} from "@manahippo/move-to-ts";
 {}
 {}
+export {{ setAddresses, getAddresses }} from '{}';
 
 export function getProjectRepo(): AptosParserRepo {{
   const repo = new AptosParserRepo();
 {}
-  repo.addDefaultParsers();
+{}
   return repo;
 }}
 
@@ -300,7 +544,122 @@ export class App {{
   }}
 }}
 "###,
-        imports, exports, loads, app_fields, app_field_inits
+        imports,
+        exports,
+        import_specifier("./addresses", false, module),
+        loads,
+        add_default_parsers(numbers),
+        app_fields,
+        app_field_inits
+    );
+
+    (filename, content)
+}
+
+/// addresses.ts: the registry of named addresses read by all generated modules. Addresses that
+/// are unassigned at compile time start out as null, until given to setAddresses
+pub fn generate_addresses(program: &Program) -> (String, String) {
+    let filename = "addresses.ts".to_string();
+    let mut addresses = BTreeMap::new();
+    for (mident, _) in program.modules.key_cloned_iter() {
+        let address = mident.value.address;
+        if let Some(name) = address_name(address) {
+            let value = match address {
+                Address::Numerical(..) => {
+                    format!("new HexString({})", quote(&format_address_hex(address)))
+                }
+                Address::NamedUnassigned(_) => "null".to_string(),
+            };
+            addresses.insert(name, value);
+        }
+    }
+    let entries = addresses
+        .iter()
+        .map(|(name, value)| format!("  {}: {},", quote(name), value))
+        .join("\n");
+
+    let content = format!(
+        r###"
+import {{ HexString, MaybeHexString }} from "aptos";
+
+type AddressListener = (address: HexString) => void;
+
+const addresses: Record<string, HexString | null> = {{
+{}
+}};
+const listeners: Record<string, AddressListener[]> = {{}};
+
+// stands in for an address that has not been assigned yet, so that modules can be imported before
+// setAddresses is called. Using it throws.
+class UnassignedAddress extends HexString {{
+  constructor(public readonly name: string) {{
+    super("0x0");
+  }}
+  private unassigned(): never {{
+    throw new Error(`Named address ${{this.name}} is not assigned, pass it to setAddresses first`);
+  }}
+  hex(): string {{
+    return this.unassigned();
+  }}
+  toString(): string {{
+    return this.unassigned();
+  }}
+  toShortString(): string {{
+    return this.unassigned();
+  }}
+  noPrefix(): string {{
+    return this.unassigned();
+  }}
+  toUint8Array(): Uint8Array {{
+    return this.unassigned();
+  }}
+}}
+
+export function setAddresses(updates: Record<string, MaybeHexString>) {{
+  for (const name of Object.keys(updates)) {{
+    // the short form is what type tags and parser keys use
+    const address = new HexString(HexString.ensure(updates[name]).toShortString());
+    addresses[name] = address;
+    for (const listener of listeners[name] || []) {{
+      listener(address);
+    }}
+  }}
+}}
+
+export function getAddresses(): Record<string, HexString | null> {{
+  return {{ ...addresses }};
+}}
+
+export function addressOf(name: string): HexString {{
+  const address = addresses[name];
+  if (!address) {{
+    throw new Error(`Named address ${{name}} is not assigned, pass it to setAddresses first`);
+  }}
+  return address;
+}}
+
+// like addressOf, but an unassigned address only throws once it is used
+export function peekAddress(name: string): HexString {{
+  return addresses[name] || new UnassignedAddress(name);
+}}
+
+export function onAddressChange(name: string, listener: AddressListener) {{
+  if (!listeners[name]) {{
+    listeners[name] = [];
+  }}
+  listeners[name].push(listener);
+}}
+
+// calls `listener` now if the address is assigned, and again whenever it changes
+export function withAddress(name: string, listener: AddressListener) {{
+  const address = addresses[name];
+  if (address) {{
+    listener(address);
+  }}
+  onAddressChange(name, listener);
+}}
+"###,
+        entries
     );
 
     (filename, content)
@@ -0,0 +1,63 @@
+use move_to_ts::report::report_note;
+use move_to_ts::shared::MoveToTsOptions;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+type Snapshot = BTreeMap<PathBuf, SystemTime>;
+
+fn collect_move_files(dir: &Path, snapshot: &mut Snapshot) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        // directory may be temporarily missing while an editor is saving
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_move_files(&path, snapshot);
+        } else if path.extension().map_or(false, |ext| ext == "move") {
+            if let Ok(modified) = entry.metadata().and_then(|meta| meta.modified()) {
+                snapshot.insert(path, modified);
+            }
+        }
+    }
+}
+
+fn take_snapshot(dirs: &[PathBuf]) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    for dir in dirs.iter() {
+        collect_move_files(dir, &mut snapshot);
+    }
+    snapshot
+}
+
+/// Polls the given directories for added, removed or modified .move files and invokes `rebuild`
+/// after each change. Never returns.
+pub fn watch<F: FnMut()>(dirs: &[PathBuf], config: &MoveToTsOptions, mut rebuild: F) -> ! {
+    let dir_names = dirs
+        .iter()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    report_note(
+        &format!("Watching {} for changes...", dir_names.join(", ")),
+        None,
+        config,
+    );
+
+    let mut last = take_snapshot(dirs);
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let current = take_snapshot(dirs);
+        if current == last {
+            continue;
+        }
+        // wait for the editor to finish writing before rebuilding
+        std::thread::sleep(POLL_INTERVAL);
+        last = take_snapshot(dirs);
+        report_note("Change detected, regenerating...", None, config);
+        rebuild();
+    }
+}
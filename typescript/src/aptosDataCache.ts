@@ -6,7 +6,7 @@ import {
   StructTag,
   TypeTag,
 } from "./typeTag.js";
-import stringify from "json-stable-stringify";
+import { stableStringify } from "./utils.js";
 
 
 export interface ITable {
@@ -282,7 +282,7 @@ export class AptosLocalCache implements AptosDataCache {
 
   table_add_box(table: ITable, key: any, value: IBox) {
     const tableMap = this.table_get_or_create(table.handle);
-    const stringKey = stringify(key);
+    const stringKey = stableStringify(key);
     if (tableMap.has(stringKey)) {
       throw new Error("key already exists");
     }
@@ -290,7 +290,7 @@ export class AptosLocalCache implements AptosDataCache {
   }
   table_borrow_box(table: ITable, key: any): IBox {
     const tableMap = this.table_get_or_create(table.handle);
-    const stringKey = stringify(key);
+    const stringKey = stableStringify(key);
     const value = tableMap.get(stringKey);
     if (!value) {
       throw new Error("key does not exist");
@@ -302,12 +302,12 @@ export class AptosLocalCache implements AptosDataCache {
   }
   table_contains_box(table: ITable, key: any): boolean {
     const tableMap = this.table_get_or_create(table.handle);
-    const stringKey = stringify(key);
+    const stringKey = stableStringify(key);
     return tableMap.has(stringKey);
   }
   table_remove_box(table: ITable, key: any): IBox {
     const tableMap = this.table_get_or_create(table.handle);
-    const stringKey = stringify(key);
+    const stringKey = stableStringify(key);
     const entry = tableMap.get(stringKey);
     if (!entry) {
       throw new Error("Key does not exist");
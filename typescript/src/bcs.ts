@@ -1,7 +1,8 @@
 import { HexString } from "aptos";
 import { BCS } from "aptos";
-import { U128, U64, U8 } from "./builtinTypes.js";
-import { u128, u64, u8 } from "./builtinFuncs.js";
+import { U128, U16, U256, U32, U64, U8, UnsignedInt } from "./builtinTypes.js";
+import { u128, u16, u256, u32, u64, u8 } from "./builtinFuncs.js";
+import bigInt from "big-integer";
 import {
   AtomicTypeTag,
   SimpleStructTag,
@@ -13,6 +14,8 @@ import {
 } from "./typeTag.js";
 import { StructInfoType } from "./parserRepo.js";
 
+const U128_MASK = (BigInt(1) << BigInt(128)) - BigInt(1);
+
 /*
 BCS serialization of Move values
 */
@@ -58,6 +61,17 @@ function addressToUint8Array(h: HexString) {
   return Uint8Array.from(buf);
 }
 
+// integers are U8...U256 objects, or bigints in code generated with --numbers bigint
+function intValue(v: any, intClass: typeof UnsignedInt, name: string): bigint {
+  if (typeof v === "bigint") {
+    return v;
+  }
+  if (!(v instanceof intClass)) {
+    throw new Error(`Expected value to be ${name}`);
+  }
+  return v.toBigInt();
+}
+
 export function serializeMoveValue(
   serializer: BCS.Serializer,
   v: any,
@@ -76,20 +90,20 @@ export function serializeMoveValue(
     }
     serializer.serializeBool(v);
   } else if (tag === AtomicTypeTag.U8) {
-    if (!(v instanceof U8)) {
-      throw new Error("Expected value to be U8");
-    }
-    serializer.serializeU8(v.toJsNumber());
+    serializer.serializeU8(Number(intValue(v, U8, "U8")));
+  } else if (tag === AtomicTypeTag.U16) {
+    serializer.serializeU16(Number(intValue(v, U16, "U16")));
+  } else if (tag === AtomicTypeTag.U32) {
+    serializer.serializeU32(Number(intValue(v, U32, "U32")));
   } else if (tag === AtomicTypeTag.U64) {
-    if (!(v instanceof U64)) {
-      throw new Error("Expected value to be U64");
-    }
-    serializer.serializeU64(v.toBigInt());
+    serializer.serializeU64(intValue(v, U64, "U64"));
   } else if (tag === AtomicTypeTag.U128) {
-    if (!(v instanceof U128)) {
-      throw new Error("Expected value to be U128");
-    }
-    serializer.serializeU128(v.toBigInt());
+    serializer.serializeU128(intValue(v, U128, "U128"));
+  } else if (tag === AtomicTypeTag.U256) {
+    const value = intValue(v, U256, "U256");
+    // little-endian: the low 128 bits come first
+    serializer.serializeU128(value & U128_MASK);
+    serializer.serializeU128(value >> BigInt(128));
   } else if (tag instanceof VectorTag) {
     serializeVector(serializer, v, tag.elementType);
   } else if (tag instanceof StructTag) {
@@ -109,10 +123,16 @@ export function serializeMoveValueWithoutTag(
 ) {
   if (value instanceof U8) {
     serializeMoveValue(serializer, value, AtomicTypeTag.U8);
+  } else if (value instanceof U16) {
+    serializeMoveValue(serializer, value, AtomicTypeTag.U16);
+  } else if (value instanceof U32) {
+    serializeMoveValue(serializer, value, AtomicTypeTag.U32);
   } else if (value instanceof U64) {
     serializeMoveValue(serializer, value, AtomicTypeTag.U64);
   } else if (value instanceof U128) {
     serializeMoveValue(serializer, value, AtomicTypeTag.U128);
+  } else if (value instanceof U256) {
+    serializeMoveValue(serializer, value, AtomicTypeTag.U256);
   } else if (typeof value === "boolean") {
     serializeMoveValue(serializer, value, AtomicTypeTag.Bool);
   } else if (value instanceof HexString) {
@@ -153,12 +173,22 @@ export function deserializeMoveValue(
   } else if (tag === AtomicTypeTag.U8) {
     const result = deserializer.deserializeU8();
     return u8(result.toString());
+  } else if (tag === AtomicTypeTag.U16) {
+    const result = deserializer.deserializeU16();
+    return u16(result.toString());
+  } else if (tag === AtomicTypeTag.U32) {
+    const result = deserializer.deserializeU32();
+    return u32(result.toString());
   } else if (tag === AtomicTypeTag.U64) {
     const result = deserializer.deserializeU64();
     return u64(result.toString());
   } else if (tag === AtomicTypeTag.U128) {
     const result = deserializer.deserializeU128();
     return u128(result.toString());
+  } else if (tag === AtomicTypeTag.U256) {
+    const low = bigInt(deserializer.deserializeU128().toString());
+    const high = bigInt(deserializer.deserializeU128().toString());
+    return u256(high.shiftLeft(128).add(low));
   } else if (tag instanceof VectorTag) {
     const length = deserializer.deserializeUleb128AsU32();
     const result: any[] = [];
@@ -0,0 +1,281 @@
+import bigInt from "big-integer";
+import {
+  U8,
+  U16,
+  U32,
+  U64,
+  U128,
+  U256,
+  UnsignedInt,
+  MutRef,
+} from "./builtinTypes.js";
+import {
+  AtomicTypeTag,
+  StructTag,
+  substituteTypeParams,
+  TypeTag,
+  VectorTag,
+} from "./typeTag.js";
+import { StructInfoType } from "./parserRepo.js";
+
+/*
+Integers of code generated with --numbers bigint. They are native bigints, so every operation that
+can leave the range of its type is checked against the width of that type.
+*/
+
+const ZERO = BigInt(0);
+
+function maxOfWidth(bits: number): bigint {
+  return (BigInt(1) << BigInt(bits)) - BigInt(1);
+}
+
+export const MAX_U8 = maxOfWidth(8);
+export const MAX_U16 = maxOfWidth(16);
+export const MAX_U32 = maxOfWidth(32);
+export const MAX_U64 = maxOfWidth(64);
+export const MAX_U128 = maxOfWidth(128);
+export const MAX_U256 = maxOfWidth(256);
+
+function checkRange(value: bigint, max: bigint, name: string): bigint {
+  if (value < ZERO || value > max) {
+    throw new Error(`Value out of bounds for ${name}: ${value}`);
+  }
+  return value;
+}
+
+export function checkU8(value: bigint): bigint {
+  return checkRange(value, MAX_U8, "U8");
+}
+
+export function checkU16(value: bigint): bigint {
+  return checkRange(value, MAX_U16, "U16");
+}
+
+export function checkU32(value: bigint): bigint {
+  return checkRange(value, MAX_U32, "U32");
+}
+
+export function checkU64(value: bigint): bigint {
+  return checkRange(value, MAX_U64, "U64");
+}
+
+export function checkU128(value: bigint): bigint {
+  return checkRange(value, MAX_U128, "U128");
+}
+
+export function checkU256(value: bigint): bigint {
+  return checkRange(value, MAX_U256, "U256");
+}
+
+// like Move, shifting by the width of the type or more aborts, and bits shifted out are lost
+function shiftLeft(value: bigint, shift: bigint, bits: number): bigint {
+  if (shift >= BigInt(bits)) {
+    throw new Error(`Shift amount out of bounds for U${bits}: ${shift}`);
+  }
+  return (value << shift) & maxOfWidth(bits);
+}
+
+function shiftRight(value: bigint, shift: bigint, bits: number): bigint {
+  if (shift >= BigInt(bits)) {
+    throw new Error(`Shift amount out of bounds for U${bits}: ${shift}`);
+  }
+  return value >> shift;
+}
+
+export function shlU8(value: bigint, shift: bigint): bigint {
+  return shiftLeft(value, shift, 8);
+}
+
+export function shlU16(value: bigint, shift: bigint): bigint {
+  return shiftLeft(value, shift, 16);
+}
+
+export function shlU32(value: bigint, shift: bigint): bigint {
+  return shiftLeft(value, shift, 32);
+}
+
+export function shlU64(value: bigint, shift: bigint): bigint {
+  return shiftLeft(value, shift, 64);
+}
+
+export function shlU128(value: bigint, shift: bigint): bigint {
+  return shiftLeft(value, shift, 128);
+}
+
+export function shlU256(value: bigint, shift: bigint): bigint {
+  return shiftLeft(value, shift, 256);
+}
+
+export function shrU8(value: bigint, shift: bigint): bigint {
+  return shiftRight(value, shift, 8);
+}
+
+export function shrU16(value: bigint, shift: bigint): bigint {
+  return shiftRight(value, shift, 16);
+}
+
+export function shrU32(value: bigint, shift: bigint): bigint {
+  return shiftRight(value, shift, 32);
+}
+
+export function shrU64(value: bigint, shift: bigint): bigint {
+  return shiftRight(value, shift, 64);
+}
+
+export function shrU128(value: bigint, shift: bigint): bigint {
+  return shiftRight(value, shift, 128);
+}
+
+export function shrU256(value: bigint, shift: bigint): bigint {
+  return shiftRight(value, shift, 256);
+}
+
+/*
+Native functions and payload builders work on U8...U256 objects. Generated code converts its
+values at these boundaries, using the static type of the argument or result.
+*/
+
+function boxInt(value: bigint, tag: AtomicTypeTag): UnsignedInt<any> {
+  const big = bigInt(value.toString());
+  switch (tag) {
+    case AtomicTypeTag.U8:
+      return new U8(big);
+    case AtomicTypeTag.U16:
+      return new U16(big);
+    case AtomicTypeTag.U32:
+      return new U32(big);
+    case AtomicTypeTag.U64:
+      return new U64(big);
+    case AtomicTypeTag.U128:
+      return new U128(big);
+    case AtomicTypeTag.U256:
+      return new U256(big);
+    default:
+      throw new Error(`Expected an integer type but received: ${tag}`);
+  }
+}
+
+/**
+ * `value` with its bigints replaced by U8...U256 objects. Vectors and structs are copied only if
+ * something in them is converted, so values without integers keep their identity.
+ */
+export function boxed(value: any, tag: TypeTag): any {
+  if (value instanceof MutRef) {
+    return boxed(value.$get(), tag);
+  } else if (typeof value === "bigint") {
+    return boxInt(value, tag as AtomicTypeTag);
+  } else if (value instanceof Array && tag instanceof VectorTag) {
+    let converted = false;
+    const elements = value.map((element) => {
+      const boxedElement = boxed(element, tag.elementType);
+      if (boxedElement !== element) {
+        converted = true;
+      }
+      return boxedElement;
+    });
+    return converted ? elements : value;
+  } else if (
+    value !== null &&
+    typeof value === "object" &&
+    value.typeTag instanceof StructTag
+  ) {
+    const structInfo = value.constructor as StructInfoType;
+    if (!structInfo.fields) {
+      return value;
+    }
+    let converted = false;
+    const proto = Object();
+    for (const field of structInfo.fields) {
+      const fieldTag = substituteTypeParams(
+        field.typeTag,
+        value.typeTag.typeParams
+      );
+      proto[field.name] = boxed(value[field.name], fieldTag);
+      if (proto[field.name] !== value[field.name]) {
+        converted = true;
+      }
+    }
+    return converted ? new structInfo(proto, value.typeTag) : value;
+  }
+  return value;
+}
+
+/**
+ * `value` with its U8...U256 objects replaced by bigints. Vectors and structs are converted in
+ * place, as natives may return references into them. Values that are already bigints, and
+ * MutRefs, are returned as they are.
+ */
+export function unboxed(value: any, tag: TypeTag): any {
+  if (value instanceof UnsignedInt) {
+    return value.toBigInt();
+  } else if (value instanceof Array && tag instanceof VectorTag) {
+    for (let i = 0; i < value.length; i++) {
+      value[i] = unboxed(value[i], tag.elementType);
+    }
+  } else if (
+    value !== null &&
+    typeof value === "object" &&
+    !(value instanceof MutRef) &&
+    value.typeTag instanceof StructTag
+  ) {
+    const structInfo = value.constructor as StructInfoType;
+    for (const field of structInfo.fields || []) {
+      const fieldTag = substituteTypeParams(
+        field.typeTag,
+        value.typeTag.typeParams
+      );
+      value[field.name] = unboxed(value[field.name], fieldTag);
+    }
+  }
+  return value;
+}
+
+/**
+ * `boxed` for a `&mut` argument of a native. Vectors and structs are converted in place, so that
+ * writes of the native land in the caller's value. A MutRef is boxed into a copy, which
+ * `writeBack` stores through the reference after the call.
+ */
+export function boxedInPlace(value: any, tag: TypeTag): any {
+  if (value instanceof MutRef) {
+    return boxed(value.$get(), tag);
+  } else if (typeof value === "bigint") {
+    return boxInt(value, tag as AtomicTypeTag);
+  } else if (value instanceof Array && tag instanceof VectorTag) {
+    for (let i = 0; i < value.length; i++) {
+      value[i] = boxedInPlace(value[i], tag.elementType);
+    }
+  } else if (
+    value !== null &&
+    typeof value === "object" &&
+    value.typeTag instanceof StructTag
+  ) {
+    const structInfo = value.constructor as StructInfoType;
+    for (const field of structInfo.fields || []) {
+      const fieldTag = substituteTypeParams(
+        field.typeTag,
+        value.typeTag.typeParams
+      );
+      value[field.name] = boxedInPlace(value[field.name], fieldTag);
+    }
+  }
+  return value;
+}
+
+/**
+ * Converts a `&mut` argument back to bigints after the native call: in place, or through the
+ * MutRef it was boxed from by `boxedInPlace`
+ */
+export function writeBack(value: any, boxedValue: any, tag: TypeTag) {
+  if (value instanceof MutRef) {
+    value.$set(unboxed(boxedValue, tag));
+  } else {
+    unboxed(value, tag);
+  }
+}
+
+/**
+ * `unboxed` for the tuple returned by a native with multiple return values
+ */
+export function unboxedTuple(values: any[], tags: TypeTag[]): any[] {
+  return values.map((value, i) => unboxed(value, tags[i]));
+}
@@ -6,9 +6,19 @@ import {
   substituteTypeParams,
 } from "./typeTag.js";
 import bigInt from "big-integer";
-import { U8, U64, U128, UnsignedInt, takeBigInt } from "./builtinTypes.js";
+import {
+  U8,
+  U16,
+  U32,
+  U64,
+  U128,
+  U256,
+  UnsignedInt,
+  MutRef,
+  takeBigInt,
+} from "./builtinTypes.js";
 import { HexString } from "aptos";
-import stringify from "json-stable-stringify";
+import { stableStringify } from "./utils.js";
 import { StructInfoType } from "./parserRepo.js";
 import { ActualStringClass } from "./nativeFuncs.js";
 
@@ -16,6 +26,8 @@ export function abortCode(code: any) {
   if (code instanceof U64) {
     // consier making it nicer by parsing the first and second byte??
     return new Error(`${code.value.toString()}`);
+  } else if (typeof code === "bigint") {
+    return new Error(`${code.toString()}`);
   }
   return code;
 }
@@ -32,6 +44,18 @@ export function u8(
   return new U8(takeBigInt(from));
 }
 
+export function u16(
+  from: UnsignedInt<any> | bigInt.BigInteger | string | number
+) {
+  return new U16(takeBigInt(from));
+}
+
+export function u32(
+  from: UnsignedInt<any> | bigInt.BigInteger | string | number
+) {
+  return new U32(takeBigInt(from));
+}
+
 export function u64(
   from: UnsignedInt<any> | bigInt.BigInteger | string | number
 ) {
@@ -44,8 +68,14 @@ export function u128(
   return new U128(takeBigInt(from));
 }
 
+export function u256(
+  from: UnsignedInt<any> | bigInt.BigInteger | string | number
+) {
+  return new U256(takeBigInt(from));
+}
+
 export function deep_eq(lhs: any, rhs: any): boolean {
-  return stringify(lhs) === stringify(rhs);
+  return stableStringify(lhs) === stableStringify(rhs);
 }
 
 export function dyn_add<T>(_tag: TypeTag, lhs: any, _rhs: any) {
@@ -125,10 +155,13 @@ export function veq(lhs: any, rhs: any): boolean {
 
 export function copy<T>(val: T): T {
   const v = val as unknown as any;
-  if (val instanceof HexString) {
+  if (val instanceof MutRef) {
+    // dereference
+    return copy(val.$get());
+  } else if (val instanceof HexString) {
     // address & signer are immutable
     return val;
-  } else if (typeof val === "boolean") {
+  } else if (typeof val === "boolean" || typeof val === "bigint") {
     return val;
   } else if (val instanceof UnsignedInt) {
     return val.copy() as unknown as T;
@@ -158,6 +191,8 @@ function printerReplacer(key: string, val: any) {
     return val;
   } else if (typeof val === "string") {
     return val;
+  } else if (typeof val === "bigint") {
+    return val.toString();
   } else if (val instanceof UnsignedInt) {
     if (val instanceof U8) {
       return val.toJsNumber();
@@ -199,14 +234,57 @@ export function print<T>(val: T) {
   console.log(JSON.stringify(val, printerReplacer, 2));
 }
 
+/**
+ * `&mut` of a variable or vector element, given as accessors. Values that are updated in place are
+ * returned as is, bool, address and bigint values are wrapped in a MutRef. Either way the result
+ * is typed as the value, like every other reference.
+ */
+export function mutRef<T>(getter: () => T, setter: (value: T) => void): T {
+  const value = getter();
+  if (
+    typeof value === "boolean" ||
+    typeof value === "bigint" ||
+    value instanceof HexString
+  ) {
+    return new MutRef(getter, setter) as unknown as T;
+  }
+  return value;
+}
+
+/**
+ * `&mut obj.field`
+ */
+export function fieldRef<T, K extends keyof T>(obj: T, field: K): T[K] {
+  return mutRef(
+    () => obj[field],
+    (value) => {
+      obj[field] = value;
+    }
+  );
+}
+
+/**
+ * Immutable references are plain values
+ */
+export function freeze<T>(val: T): T {
+  if (val instanceof MutRef) {
+    return val.$get();
+  }
+  return val;
+}
+
 export function set(lhs: any, rhs: any) {
-  if (lhs instanceof HexString) {
+  if (lhs instanceof MutRef) {
+    lhs.$set(rhs);
+  } else if (lhs instanceof HexString) {
     if (!(rhs instanceof HexString)) {
       throw new Error("Expect both lhs and rhs to be HexString!");
     }
     (lhs as unknown as any).hexString = (rhs as unknown as any).hexString;
   } else if (typeof lhs === "boolean") {
     throw new Error("Mutating boolean value by reference not supported");
+  } else if (typeof lhs === "bigint") {
+    throw new Error("Mutating bigint value by reference not supported");
   } else if (lhs instanceof UnsignedInt) {
     if (!(rhs instanceof UnsignedInt)) {
       throw new Error("Expect both lhs and rhs to be UnsignedInt!");
@@ -230,8 +308,10 @@ export function set(lhs: any, rhs: any) {
   }
 }
 
-export function u8str(array: U8[]): string {
-  const u8array = new Uint8Array(array.map((u) => u.toJsNumber()));
+export function u8str(array: (U8 | bigint)[]): string {
+  const u8array = new Uint8Array(
+    array.map((u) => (typeof u === "bigint" ? Number(u) : u.toJsNumber()))
+  );
   return new TextDecoder().decode(u8array);
 }
 
@@ -244,13 +324,23 @@ export function strToU8(str: string): U8[] {
 }
 
 export function payloadArg(val: any) {
-  if (val instanceof UnsignedInt) {
-    if (val instanceof U8) {
+  if (typeof val === "bigint") {
+    // the width is unknown here: code generated with --numbers bigint boxes its arguments first
+    return val.toString();
+  } else if (val instanceof UnsignedInt) {
+    // integers up to 32 bits are JSON numbers, wider ones are strings
+    if (val instanceof U8 || val instanceof U16 || val instanceof U32) {
       return val.toJsNumber();
-    } else if (val instanceof U64 || val instanceof U128) {
+    } else if (
+      val instanceof U64 ||
+      val instanceof U128 ||
+      val instanceof U256
+    ) {
       return val.value.toString();
     } else {
-      throw new Error("Only expect U8, U64, or U128 for integer types");
+      throw new Error(
+        "Only expect U8, U16, U32, U64, U128, or U256 for integer types"
+      );
     }
   } else if (val instanceof HexString) {
     return val.toShortString();
@@ -274,15 +364,26 @@ export function payloadArg(val: any) {
   }
 }
 
-export function u8ArrayArg(val: U8[]): string {
-  const uint8array = new Uint8Array(Array.from(val.map((u) => u.toJsNumber())));
+export function u8ArrayArg(val: (U8 | bigint)[]): string {
+  const uint8array = new Uint8Array(
+    val.map((u) => (typeof u === "bigint" ? Number(u) : u.toJsNumber()))
+  );
   return HexString.fromUint8Array(uint8array).hex();
 }
 
 export function moveValueToOpenApiObject(val: any, typeTag: TypeTag): any {
-  if (val instanceof U8) {
+  if (typeof val === "bigint") {
+    if (
+      typeTag === AtomicTypeTag.U8 ||
+      typeTag === AtomicTypeTag.U16 ||
+      typeTag === AtomicTypeTag.U32
+    ) {
+      return Number(val);
+    }
+    return val.toString();
+  } else if (val instanceof U8 || val instanceof U16 || val instanceof U32) {
     return val.toJsNumber();
-  } else if (val instanceof U64 || val instanceof U128) {
+  } else if (val instanceof U64 || val instanceof U128 || val instanceof U256) {
     return val.value.toString();
   } else if (val instanceof HexString) {
     return val.hex();
@@ -122,6 +122,36 @@ export class U8 extends UnsignedInt<U8> {
   }
 }
 
+export class U16 extends UnsignedInt<U16> {
+  static MIN = bigInt(0);
+  static MAX = bigInt("65535");
+
+  make(value: bigInt.BigInteger) {
+    return new U16(value);
+  }
+
+  checkBounds() {
+    if (this.value.lt(U16.MIN) || this.value.gt(U16.MAX)) {
+      throw new Error(`Value out of bounds for U16: ${this.value}`);
+    }
+  }
+}
+
+export class U32 extends UnsignedInt<U32> {
+  static MIN = bigInt(0);
+  static MAX = bigInt("4294967295");
+
+  make(value: bigInt.BigInteger) {
+    return new U32(value);
+  }
+
+  checkBounds() {
+    if (this.value.lt(U32.MIN) || this.value.gt(U32.MAX)) {
+      throw new Error(`Value out of bounds for U32: ${this.value}`);
+    }
+  }
+}
+
 export class U64 extends UnsignedInt<U64> {
   static MIN = bigInt(0);
   static MAX = bigInt("18446744073709551615");
@@ -151,3 +181,35 @@ export class U128 extends UnsignedInt<U128> {
     }
   }
 }
+
+export class U256 extends UnsignedInt<U256> {
+  static MIN = bigInt(0);
+  static MAX = bigInt("115792089237316195423570985008687907853269984665640564039457584007913129639935");
+
+  make(value: bigInt.BigInteger) {
+    return new U256(value);
+  }
+
+  checkBounds() {
+    if (this.value.lt(U256.MIN) || this.value.gt(U256.MAX)) {
+      throw new Error(`Value out of bounds for U256: ${this.value}`);
+    }
+  }
+}
+
+/**
+ * A mutable reference to a value that is not updated in place (bool, address). Reads and writes go
+ * through to the variable, struct field or vector element it was borrowed from. References to
+ * integers, vectors and structs are the objects themselves.
+ */
+export class MutRef<T> {
+  constructor(private getter: () => T, private setter: (value: T) => void) {}
+
+  $get(): T {
+    return this.getter();
+  }
+
+  $set(value: T) {
+    this.setter(value);
+  }
+}
@@ -1,6 +1,7 @@
 export * from "./aptosDataCache.js";
 export * from "./builtinTypes.js";
 export * from "./builtinFuncs.js";
+export * from "./bigintFuncs.js";
 export * from "./nativeFuncs.js";
 export * from "./jsonTypes.js";
 export * from "./parserRepo.js";
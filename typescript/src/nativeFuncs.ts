@@ -20,9 +20,10 @@ import {
   parseStructProto,
   TypeParamDeclType,
 } from "./parserRepo.js";
-import { strToU8, u64, u8str } from "./builtinFuncs.js";
+import { mutRef, strToU8, u64, u8str } from "./builtinFuncs.js";
 import { BCS } from "aptos";
 import { deserializeMoveValue, serializeMoveValue } from "./bcs.js";
+import { bigintReplacer } from "./utils.js";
 
 /*
 native functions for BCS serialization/deserialization
@@ -79,7 +80,7 @@ export function aptos_std_debug_print(
   $c: AptosDataCache,
   _: TypeTag[]
 ) {
-  console.log(JSON.stringify(v, null, 2));
+  console.log(JSON.stringify(v, bigintReplacer, 2));
 }
 
 export function aptos_std_debug_print_stack_trace($c: AptosDataCache) {
@@ -250,8 +251,12 @@ export function std_vector_borrow_mut(
   if (idx >= vec.length) {
     throw new Error(`Index out of bounds: ${idx} >= ${vec.length}`);
   }
-  // FIXME: we should probably wrap &mut in a MutRef object with a $set method
-  return vec[idx];
+  return mutRef(
+    () => vec[idx],
+    (value) => {
+      vec[idx] = value;
+    }
+  );
 }
 
 export function std_vector_pop_back(
@@ -9,7 +9,7 @@ import {
 } from "./typeTag.js";
 import { AptosClient, HexString } from "aptos";
 import bigInt from "big-integer";
-import { U128, U64, U8 } from "./builtinTypes.js";
+import { U128, U16, U256, U32, U64, U8 } from "./builtinTypes.js";
 import { strToU8, u8 } from "./builtinFuncs.js";
 
 export type TypeParamDeclType = {
@@ -143,6 +143,58 @@ export function U8Parser(
   return new U8(bigInt(data));
 }
 
+export function U16Parser(
+  data: any,
+  typeTag: TypeTag,
+  _repo: AptosParserRepo
+): U16 {
+  if (typeTag !== AtomicTypeTag.U16) {
+    throw new Error(
+      `U16Parser cannot parse type: ${getTypeTagParamlessName(typeTag)}`
+    );
+  }
+  if (typeof data !== "number") {
+    throw new Error(
+      `U16Parser expects number type as data but received: ${typeof data}`
+    );
+  }
+  if (data < 0 || data > 65535) {
+    throw new Error(
+      `U16Parser expects a number between 0 and 65535, but received: ${data}`
+    );
+  }
+  if (!Number.isInteger(data)) {
+    throw new Error(`U16Parser expects an integer but received: ${data}`);
+  }
+  return new U16(bigInt(data));
+}
+
+export function U32Parser(
+  data: any,
+  typeTag: TypeTag,
+  _repo: AptosParserRepo
+): U32 {
+  if (typeTag !== AtomicTypeTag.U32) {
+    throw new Error(
+      `U32Parser cannot parse type: ${getTypeTagParamlessName(typeTag)}`
+    );
+  }
+  if (typeof data !== "number") {
+    throw new Error(
+      `U32Parser expects number type as data but received: ${typeof data}`
+    );
+  }
+  if (data < 0 || data > 4294967295) {
+    throw new Error(
+      `U32Parser expects a number between 0 and 4294967295, but received: ${data}`
+    );
+  }
+  if (!Number.isInteger(data)) {
+    throw new Error(`U32Parser expects an integer but received: ${data}`);
+  }
+  return new U32(bigInt(data));
+}
+
 export function U64Parser(
   data: any,
   typeTag: TypeTag,
@@ -179,6 +231,24 @@ export function U128Parser(
   return new U128(bigInt(data));
 }
 
+export function U256Parser(
+  data: any,
+  typeTag: TypeTag,
+  _repo: AptosParserRepo
+): U256 {
+  if (typeTag !== AtomicTypeTag.U256) {
+    throw new Error(
+      `U256Parser cannot parse type: ${getTypeTagParamlessName(typeTag)}`
+    );
+  }
+  if (typeof data !== "string") {
+    throw new Error(
+      `U256Parser expects string type as data but received: ${typeof data}`
+    );
+  }
+  return new U256(bigInt(data));
+}
+
 export function BoolParser(
   data: any,
   typeTag: TypeTag,
@@ -274,6 +344,9 @@ export function VectorParser(
       );
     }
     const hex = new HexString(data);
+    if (repo.bigint) {
+      return Array.from(hex.toUint8Array()).map((u) => BigInt(u));
+    }
     return Array.from(hex.toUint8Array()).map((u) => u8(u));
   }
   if (!(data instanceof Array)) {
@@ -295,10 +368,21 @@ export function VectorParser(
   return vector;
 }
 
+/**
+ * An integer parser that returns a bigint instead of a U8...U256 object
+ */
+function bigIntParser(parser: ParserFunc): ParserFunc {
+  return (data: any, typeTag: TypeTag, repo: AptosParserRepo): bigint =>
+    parser(data, typeTag, repo).toBigInt();
+}
+
 export class AptosParserRepo {
   paramlessNameToParser: Record<string, ParserFunc>;
+  // integers are parsed to bigints, see addBigIntParsers
+  bigint: boolean;
   constructor() {
     this.paramlessNameToParser = {};
+    this.bigint = false;
   }
   async loadResource(
     client: AptosClient,
@@ -376,9 +460,22 @@ export class AptosParserRepo {
     // load the defaults
     this.addParser("bool", BoolParser);
     this.addParser("u8", U8Parser);
+    this.addParser("u16", U16Parser);
+    this.addParser("u32", U32Parser);
     this.addParser("u64", U64Parser);
     this.addParser("u128", U128Parser);
+    this.addParser("u256", U256Parser);
     this.addParser("address", AddressParser);
     this.addParser("vector", VectorParser);
   }
+  // for code generated with --numbers bigint
+  addBigIntParsers() {
+    this.bigint = true;
+    this.addParser("u8", bigIntParser(U8Parser));
+    this.addParser("u16", bigIntParser(U16Parser));
+    this.addParser("u32", bigIntParser(U32Parser));
+    this.addParser("u64", bigIntParser(U64Parser));
+    this.addParser("u128", bigIntParser(U128Parser));
+    this.addParser("u256", bigIntParser(U256Parser));
+  }
 }
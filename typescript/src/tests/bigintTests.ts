@@ -0,0 +1,202 @@
+import { HexString } from "aptos";
+import {
+  boxed,
+  boxedInPlace,
+  checkU8,
+  checkU64,
+  checkU256,
+  MAX_U64,
+  MAX_U256,
+  shlU8,
+  shlU64,
+  shrU8,
+  shrU256,
+  unboxed,
+  writeBack,
+} from "../bigintFuncs.js";
+import { mutRef, u64 } from "../builtinFuncs.js";
+import { MutRef, U64, U8 } from "../builtinTypes.js";
+import {
+  AptosParserRepo,
+  FieldDeclType,
+  TypeParamDeclType,
+} from "../parserRepo.js";
+import { AtomicTypeTag, StructTag, TypeTag, VectorTag } from "../typeTag.js";
+import { assert } from "../utils.js";
+
+function assertThrows(f: () => any, name: string) {
+  let threw = false;
+  try {
+    f();
+  } catch (e) {
+    threw = true;
+  }
+  assert(threw, `${name} should have thrown`);
+}
+
+class Counter {
+  static moduleAddress = new HexString("0x1");
+  static moduleName = "counter";
+  static structName: string = "Counter";
+  static typeParameters: TypeParamDeclType[] = [];
+  static fields: FieldDeclType[] = [
+    { name: "value", typeTag: AtomicTypeTag.U64 },
+    { name: "history", typeTag: new VectorTag(AtomicTypeTag.U8) },
+    { name: "enabled", typeTag: AtomicTypeTag.Bool },
+  ];
+
+  value: any;
+  history: any;
+  enabled: any;
+
+  constructor(proto: any, public typeTag: TypeTag) {
+    this.value = proto["value"];
+    this.history = proto["history"];
+    this.enabled = proto["enabled"];
+  }
+}
+
+const counterTag = new StructTag(
+  new HexString("0x1"),
+  "counter",
+  "Counter",
+  []
+);
+
+export function test() {
+  function testChecks() {
+    assert(checkU8(BigInt(0)) === BigInt(0));
+    assert(checkU8(BigInt(255)) === BigInt(255));
+    assertThrows(() => checkU8(BigInt(256)), "checkU8(256)");
+    assertThrows(() => checkU8(BigInt(-1)), "checkU8(-1)");
+    assert(checkU64(MAX_U64) === MAX_U64);
+    assertThrows(() => checkU64(MAX_U64 + BigInt(1)), "checkU64(MAX_U64 + 1)");
+    assert(checkU256(MAX_U256) === MAX_U256);
+    assertThrows(
+      () => checkU256(MAX_U256 + BigInt(1)),
+      "checkU256(MAX_U256 + 1)"
+    );
+  }
+  testChecks();
+
+  function testShifts() {
+    assert(shlU8(BigInt(1), BigInt(7)) === BigInt(128));
+    // bits shifted out are lost
+    assert(shlU8(BigInt(255), BigInt(1)) === BigInt(254));
+    assert(shlU64(MAX_U64, BigInt(63)) === BigInt(1) << BigInt(63));
+    assert(shrU8(BigInt(128), BigInt(7)) === BigInt(1));
+    assert(shrU256(MAX_U256, BigInt(255)) === BigInt(1));
+    // shifting by the width or more aborts
+    assertThrows(() => shlU8(BigInt(1), BigInt(8)), "shlU8 by 8");
+    assertThrows(() => shlU64(BigInt(1), BigInt(64)), "shlU64 by 64");
+    assertThrows(() => shrU8(BigInt(1), BigInt(8)), "shrU8 by 8");
+    assertThrows(() => shrU256(BigInt(1), BigInt(256)), "shrU256 by 256");
+  }
+  testShifts();
+
+  function testBoxingRoundTrip() {
+    const boxedU64 = boxed(BigInt(7), AtomicTypeTag.U64);
+    assert(boxedU64 instanceof U64);
+    assert(unboxed(boxedU64, AtomicTypeTag.U64) === BigInt(7));
+
+    const vectorTag = new VectorTag(AtomicTypeTag.U8);
+    const vector = [BigInt(1), BigInt(2)];
+    const boxedVector = boxed(vector, vectorTag);
+    // copied, so the original keeps its bigints
+    assert(boxedVector !== vector);
+    assert(typeof vector[0] === "bigint");
+    assert(boxedVector[0] instanceof U8 && boxedVector[1] instanceof U8);
+    const unboxedVector = unboxed(boxedVector, vectorTag);
+    assert(unboxedVector[0] === BigInt(1) && unboxedVector[1] === BigInt(2));
+
+    // values without integers keep their identity
+    const bools = [true, false];
+    assert(boxed(bools, new VectorTag(AtomicTypeTag.Bool)) === bools);
+
+    const counter = new Counter(
+      { value: BigInt(3), history: [BigInt(4)], enabled: true },
+      counterTag
+    );
+    const boxedCounter = boxed(counter, counterTag);
+    assert(boxedCounter instanceof Counter && boxedCounter !== counter);
+    assert(boxedCounter.value instanceof U64);
+    assert(boxedCounter.history[0] instanceof U8);
+    assert(boxedCounter.enabled === true);
+    assert(counter.value === BigInt(3));
+    const unboxedCounter = unboxed(boxedCounter, counterTag);
+    assert(unboxedCounter.value === BigInt(3));
+    assert(unboxedCounter.history[0] === BigInt(4));
+
+    // a MutRef is dereferenced
+    let local = BigInt(9);
+    const ref = mutRef(
+      () => local,
+      (v) => {
+        local = v;
+      }
+    );
+    assert(ref instanceof MutRef);
+    assert(boxed(ref, AtomicTypeTag.U64).toBigInt() === BigInt(9));
+  }
+  testBoxingRoundTrip();
+
+  function testWriteBack() {
+    // vectors and structs are converted in place, so a native's writes are kept
+    const vectorTag = new VectorTag(AtomicTypeTag.U64);
+    const vector = [BigInt(1)];
+    const boxedVector = boxedInPlace(vector, vectorTag);
+    assert(boxedVector === vector && vector[0] instanceof U64);
+    boxedVector.push(u64(2));
+    writeBack(vector, boxedVector, vectorTag);
+    assert(vector.length === 2);
+    assert(vector[0] === BigInt(1) && vector[1] === BigInt(2));
+
+    const counter = new Counter(
+      { value: BigInt(3), history: [], enabled: false },
+      counterTag
+    );
+    const boxedCounter = boxedInPlace(counter, counterTag);
+    assert(boxedCounter === counter && counter.value instanceof U64);
+    boxedCounter.value = u64(4);
+    writeBack(counter, boxedCounter, counterTag);
+    assert(counter.value === BigInt(4));
+
+    // a MutRef is written through
+    let local = BigInt(5);
+    const ref = mutRef(
+      () => local,
+      (v) => {
+        local = v;
+      }
+    );
+    const boxedLocal = boxedInPlace(ref, AtomicTypeTag.U64);
+    assert(boxedLocal instanceof U64);
+    writeBack(ref, u64(6), AtomicTypeTag.U64);
+    assert(local === BigInt(6));
+  }
+  testWriteBack();
+
+  function testBigIntParsers() {
+    const repo = new AptosParserRepo();
+    repo.addDefaultParsers();
+    assert(repo.parse("7" as any, AtomicTypeTag.U64) instanceof U64);
+
+    repo.addBigIntParsers();
+    assert(repo.parse("7" as any, AtomicTypeTag.U64) === BigInt(7));
+    const maxU128 = (BigInt(1) << BigInt(128)) - BigInt(1);
+    const parsed = repo.parse(maxU128.toString() as any, AtomicTypeTag.U128);
+    assert(parsed === maxU128);
+    assert(repo.parse(255 as any, AtomicTypeTag.U8) === BigInt(255));
+    const bytes = repo.parse("0x0102" as any, new VectorTag(AtomicTypeTag.U8));
+    assert(bytes.length === 2);
+    assert(bytes[0] === BigInt(1) && bytes[1] === BigInt(2));
+    const u64s = repo.parse(
+      ["1", "2"] as any,
+      new VectorTag(AtomicTypeTag.U64)
+    );
+    assert(u64s[0] === BigInt(1) && u64s[1] === BigInt(2));
+  }
+  testBigIntParsers();
+
+  console.log("Passed: bigint");
+}
@@ -1,3 +1,5 @@
+import { test as bigintTest } from "./bigintTests.js";
+import { test as mutRefTest } from "./mutRefTests.js";
 import { test } from "./typeTagTests.js";
 
 const main = async () => {
@@ -6,6 +8,8 @@ const main = async () => {
   switch (fname) {
     case "test":
       await test();
+      await bigintTest();
+      await mutRefTest();
       break;
     default:
       console.log(`Unrecognized command: ${fname}`);
@@ -0,0 +1,79 @@
+import { HexString } from "aptos";
+import { DummyCache } from "../aptosDataCache.js";
+import { copy, fieldRef, freeze, mutRef, set, u64 } from "../builtinFuncs.js";
+import { MutRef, U64 } from "../builtinTypes.js";
+import { std_vector_borrow_mut } from "../nativeFuncs.js";
+import { AtomicTypeTag, TypeTag } from "../typeTag.js";
+import { assert } from "../utils.js";
+
+// what `fun assign<T: copy + drop>(r: &mut T, v: T) { *r = v; }` compiles to
+function assign(r: any, v: any, $c: DummyCache, $p: TypeTag[]) {
+  set(r, copy(v));
+}
+
+export function test() {
+  const $c = new DummyCache();
+
+  function testBoolLocal() {
+    let flag = false;
+    const r = mutRef(
+      () => flag,
+      (v) => {
+        flag = v;
+      }
+    );
+    assert(r instanceof MutRef);
+    assert(freeze(r) === false);
+    set(r, true);
+    assert(flag === true);
+    assert(freeze(r) === true);
+  }
+  testBoolLocal();
+
+  function testAddressField() {
+    const holder = { owner: new HexString("0x1") };
+    const r = fieldRef(holder, "owner");
+    assert(r instanceof MutRef);
+    set(r, new HexString("0x2"));
+    assert(holder.owner.hex() === "0x2");
+    assert(freeze(r).hex() === "0x2");
+  }
+  testAddressField();
+
+  function testBoolVectorElement() {
+    const flags = [false, false, true];
+    const r = std_vector_borrow_mut(flags, u64(1), $c, [AtomicTypeTag.Bool]);
+    assert(r instanceof MutRef);
+    set(r, true);
+    assert(flags[0] === false && flags[1] === true && flags[2] === true);
+  }
+  testBoolVectorElement();
+
+  function testGeneric() {
+    let flag = false;
+    const boolRef = mutRef(
+      () => flag,
+      (v) => {
+        flag = v;
+      }
+    );
+    assign(boolRef, true, $c, [AtomicTypeTag.Bool]);
+    assert(flag === true);
+
+    // u64 values are updated in place, so no MutRef is involved
+    const value = u64(1);
+    const u64Ref = mutRef(
+      () => value,
+      () => {
+        throw new Error("u64 should not be written through the setter");
+      }
+    );
+    assert(u64Ref === value);
+    assign(u64Ref, u64(2), $c, [AtomicTypeTag.U64]);
+    assert(value instanceof U64 && value.eq(u64(2)));
+    assert(freeze(u64Ref) === value);
+  }
+  testGeneric();
+
+  console.log("Passed: mutRef");
+}
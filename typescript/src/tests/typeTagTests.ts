@@ -64,6 +64,9 @@ export function test() {
   genericTest("vector<u8>");
   genericTest("vector<address>");
   genericTest("vector<u128>");
+  genericTest("u16");
+  genericTest("vector<u32>");
+  genericTest("0x1::M::S<u256, vector<u16>>");
   genericTest("vector<0x1::M::s>");
   genericTest(
     "0x1::M::S<0x1::M::s, u8, 0x2::MM::TT, 0x2::MM::TT<u8, address, 0x2::M::T>, address, vector<0x1::M::S<u8>>>"
@@ -13,7 +13,7 @@ const { AccountAddress, Identifier, ModuleId, EntryFunction } = TxnBuilderTypes;
 
 import { AptosParserRepo } from "./parserRepo.js";
 import { StructTag } from "./typeTag.js";
-import { U128, U64, U8 } from "./builtinTypes.js";
+import { U128, U16, U256, U32, U64, U8 } from "./builtinTypes.js";
 import {
   ActualStringClass,
   payloadArg,
@@ -23,8 +23,11 @@ import {
 type AcceptedScriptFuncArgType =
   | any[]
   | U8
+  | U16
+  | U32
   | U64
   | U128
+  | U256
   | HexString
   | boolean
   | ActualStringClass;
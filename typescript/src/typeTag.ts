@@ -6,8 +6,11 @@ import { assert } from "./utils.js";
 export enum AtomicTypeTag {
   Bool = "bool",
   U8 = "u8",
+  U16 = "u16",
+  U32 = "u32",
   U64 = "u64",
   U128 = "u128",
+  U256 = "u256",
   Address = "address",
   Signer = "signer", // only needed for script function interface
 }
@@ -1,8 +1,10 @@
+import stringify from "json-stable-stringify";
+
 export function print(obj: any) {
   if (typeof obj === "object") {
     console.log(obj.constructor.name);
   }
-  console.log(JSON.stringify(obj, null, 2));
+  console.log(JSON.stringify(obj, bigintReplacer, 2));
 }
 
 export function assert(cond: boolean, msg = "") {
@@ -10,3 +12,12 @@ export function assert(cond: boolean, msg = "") {
     throw new Error(msg);
   }
 }
+
+// bigints, the integers of code generated with --numbers bigint, have no JSON representation
+export function bigintReplacer(_key: string, value: any) {
+  return typeof value === "bigint" ? value.toString() : value;
+}
+
+export function stableStringify(value: any): string {
+  return stringify(value, { replacer: bigintReplacer });
+}